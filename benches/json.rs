@@ -0,0 +1,120 @@
+use alder::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::sync::Arc;
+
+node_ids! {
+    pub Json:
+        WS,
+        Comment,
+        Value,
+        Array,
+        Object,
+        Key
+}
+
+fn extra() -> Arc<dyn Parser> {
+    v_node(NodeId::EXTRA, |state| loop {
+        if state.peek(1).as_ref().is_ws() {
+            state.add(recognize(Json::WS, chomp_while(is_ws)));
+        } else if state.peek(2).as_ref() == "//" {
+            state.add(recognize(Json::Comment, chomp_until(is_line_ending)));
+        } else if state.peek(2).as_ref() == "/*" {
+            state.add(block_comment("/*", "*/", false));
+        } else {
+            break;
+        }
+    })
+    .arc()
+}
+
+fn json() -> Grammar {
+    Grammar::new("value")
+        .rule("value", |refs| {
+            let array = refs.rule("array");
+            let object = refs.rule("object");
+            v_node(Json::Value, move |state| match state.peek(1).as_ref() {
+                "[" => state.add(array),
+                "{" => state.add(object),
+                "\"" => state.add(string_literal(StringFormat::json())),
+                "t" => state.add(keyword("true")),
+                "f" => state.add(keyword("false")),
+                "n" => state.add(keyword("null")),
+                _ => state.add(number(NumberFormat::json())),
+            })
+        })
+        .rule("array", |refs| {
+            let value = refs.rule("value");
+            node(Json::Array, move |state| {
+                state.add(delimited_list(
+                    "[",
+                    value,
+                    ",",
+                    "]",
+                    "I expected `,` or `]`",
+                ));
+            })
+        })
+        .rule("object", |refs| {
+            let value = refs.rule("value");
+            node(Json::Object, move |state| {
+                let pair = v_node(None, |state| {
+                    state.add(field(Json::Key, string_literal(StringFormat::json())));
+                    state.add(recover(":"));
+                    state.add(value);
+                });
+                state.add(delimited_list("{", pair, ",", "}", "I expected `,` or `}`"));
+            })
+        })
+}
+
+fn parse(input: &str) -> Parsed {
+    Parse::new(json()).extra(extra()).complete(true).run(input)
+}
+
+/// Array of small objects, roughly `size` bytes long.
+fn document(size: usize) -> String {
+    let mut output = String::from("[\n");
+    let mut id = 0;
+    while output.len() < size {
+        output.push_str(&format!(
+            r#"  {{ "id": {}, "name": "item \"{}\"", "tags": ["a", "b"], "ok": true }},"#,
+            id, id
+        ));
+        output.push('\n');
+        id += 1;
+    }
+    output.push_str("  null\n]");
+    output
+}
+
+fn comment(size: usize) -> String {
+    format!("// {}\ntrue", "lorem ipsum ".repeat(size / 12))
+}
+
+fn string(size: usize) -> String {
+    format!(r#""{}""#, "zażółć gęślą jaźń ".repeat(size / 30))
+}
+
+fn scaling(c: &mut Criterion, name: &str, generate: fn(usize) -> String) {
+    let mut group = c.benchmark_group(name);
+    for size in [1_000, 10_000, 100_000].iter() {
+        let input = generate(*size);
+        let parsed = parse(&input);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &input, |b, input| {
+            b.iter(|| parse(input))
+        });
+    }
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    scaling(c, "document", document);
+    scaling(c, "comment", comment);
+    scaling(c, "string", string);
+}
+
+criterion_group!(scaling_benches, benches);
+criterion_main!(scaling_benches);
@@ -4,6 +4,7 @@ use proc_macro::TokenStream;
 
 mod macro_alder;
 mod macro_ast;
+mod macro_lexer;
 mod test_case;
 mod utils;
 
@@ -16,3 +17,12 @@ pub fn alder_test(_args: TokenStream, input: TokenStream) -> TokenStream {
 pub fn ast(input: TokenStream) -> TokenStream {
     macro_ast::ast(input)
 }
+
+/// Longest-match `Lexer` declared with `#[token("{")]`, `#[regex("[0-9]+")]` and `#[trivia]` on enum variants.
+///
+/// Generates `<Enum>Kind` (or `#[lexer(ids = "Name")]`) struct with `NodeId` constants,
+/// which implements `Lexer`. Regex rules need `regex` feature of `alder`.
+#[proc_macro_derive(Lexer, attributes(lexer, token, regex, trivia))]
+pub fn lexer(input: TokenStream) -> TokenStream {
+    macro_lexer::lexer(input)
+}
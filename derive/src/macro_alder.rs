@@ -32,6 +32,7 @@ pub fn alder_test(input: TokenStream) -> TokenStream {
             Lit::Str(s) => Some(s.value()),
             _ => None,
         })
+        .map(trim_last_blank_line)
         .map(|lit| lit.unindent())
         .map(|lit| lit.trim_start().to_string())
         .map(|lit| TestCase::new(fn_name.clone(), lit))
@@ -51,3 +52,13 @@ pub fn alder_test(input: TokenStream) -> TokenStream {
 
     out.into()
 }
+
+/// Newer compilers keep the indentation before `*/` of block doc comments as the last line.
+fn trim_last_blank_line(mut lit: String) -> String {
+    if let Some(i) = lit.rfind('\n') {
+        if lit[i..].trim().is_empty() {
+            lit.truncate(i);
+        }
+    }
+    lit
+}
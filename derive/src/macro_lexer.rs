@@ -0,0 +1,186 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+
+use darling::ast;
+use darling::{FromDeriveInput, FromVariant};
+use quote::{format_ident, quote, ToTokens};
+use syn::{parse_macro_input, DeriveInput, Lit, Meta, NestedMeta};
+
+#[derive(Debug, FromDeriveInput)]
+#[darling(attributes(lexer), supports(enum_unit))]
+struct LexerInputReceiver {
+    ident: syn::Ident,
+    vis: syn::Visibility,
+    data: ast::Data<LexerVariantReceiver, ()>,
+    #[darling(default)]
+    ids: Option<syn::Ident>,
+}
+
+#[derive(Debug, FromVariant)]
+#[darling(forward_attrs(token, regex, trivia))]
+struct LexerVariantReceiver {
+    ident: syn::Ident,
+    attrs: Vec<syn::Attribute>,
+}
+
+enum Rule {
+    Token(syn::LitStr),
+    Regex(syn::LitStr),
+}
+
+struct Variant {
+    ident: syn::Ident,
+    rules: Vec<Rule>,
+    trivia: bool,
+    name: String,
+}
+
+/// `#[token("{")]`, `#[regex("[0-9]+", name = "a number")]` or `#[trivia]`.
+fn parse_variant(variant: &LexerVariantReceiver) -> syn::Result<Variant> {
+    let mut rules = vec![];
+    let mut trivia = false;
+    let mut name = None;
+    for attr in &variant.attrs {
+        let list = match attr.parse_meta()? {
+            Meta::Path(path) if path.is_ident("trivia") => {
+                trivia = true;
+                continue;
+            }
+            Meta::List(list) => list,
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "Expected `#[token(\"...\")]`",
+                ))
+            }
+        };
+        let mut nested = list.nested.iter();
+        let pattern = match nested.next() {
+            Some(NestedMeta::Lit(Lit::Str(pattern))) => pattern.clone(),
+            _ => return Err(syn::Error::new_spanned(&list, "Expected string literal")),
+        };
+        for meta in nested {
+            match meta {
+                NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("name") => {
+                    match &pair.lit {
+                        Lit::Str(lit) => name = Some(lit.value()),
+                        lit => return Err(syn::Error::new_spanned(lit, "Expected string literal")),
+                    }
+                }
+                meta => return Err(syn::Error::new_spanned(meta, "Expected `name = \"...\"`")),
+            }
+        }
+        if list.path.is_ident("token") {
+            name = name.or_else(|| Some(format!("`{}`", pattern.value())));
+            rules.push(Rule::Token(pattern));
+        } else {
+            rules.push(Rule::Regex(pattern));
+        }
+    }
+    let ident = variant.ident.clone();
+    let name = name.unwrap_or_else(|| format!("`{}`", ident));
+    Ok(Variant {
+        ident,
+        rules,
+        trivia,
+        name,
+    })
+}
+
+impl ToTokens for LexerInputReceiver {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let LexerInputReceiver {
+            ref ident,
+            ref vis,
+            ref data,
+            ref ids,
+        } = *self;
+
+        let ids = ids
+            .clone()
+            .unwrap_or_else(|| format_ident!("{}Kind", ident));
+        let variants = match data
+            .as_ref()
+            .take_enum()
+            .unwrap_or_default()
+            .into_iter()
+            .map(parse_variant)
+            .collect::<syn::Result<Vec<_>>>()
+        {
+            Ok(variants) => variants,
+            Err(e) => {
+                tokens.extend(e.to_compile_error());
+                return;
+            }
+        };
+
+        let idents = variants.iter().map(|v| &v.ident).collect::<Vec<_>>();
+        let names = variants.iter().map(|v| &v.name).collect::<Vec<_>>();
+        let trivia = variants.iter().filter(|v| v.trivia).map(|v| &v.ident);
+        let ids_ref = &ids;
+        let rules = variants.iter().flat_map(|v| {
+            let (ids, variant) = (ids_ref, &v.ident);
+            v.rules.iter().map(move |rule| match rule {
+                Rule::Token(token) => quote! { .token(#ids::#variant, #token) },
+                Rule::Regex(pattern) => quote! { .regex(#ids::#variant, #pattern) },
+            })
+        });
+
+        tokens.extend(quote! {
+            #vis struct #ids;
+
+            #[allow(non_upper_case_globals)]
+            impl #ids {
+                #( #vis const #idents: NodeId = NodeId(stringify!(#idents)); )*
+            }
+
+            impl From<#ident> for NodeId {
+                fn from(token: #ident) -> Self {
+                    match token {
+                        #( #ident::#idents => #ids::#idents, )*
+                    }
+                }
+            }
+
+            impl std::fmt::Display for #ident {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    let name = match self {
+                        #( #ident::#idents => #names, )*
+                    };
+                    f.write_str(name)
+                }
+            }
+
+            impl Lexer for #ids {
+                fn lex(&self, input: &str) -> Option<(NodeId, usize)> {
+                    thread_local! {
+                        static RULES: Rules = Rules::new() #(#rules)*;
+                    }
+                    RULES.with(|rules| rules.longest(input))
+                }
+
+                fn is_trivia(&self, kind: NodeId) -> bool {
+                    #( kind == #ids::#trivia || )* false
+                }
+
+                fn describe(&self, kind: NodeId) -> String {
+                    match kind {
+                        #( #ids::#idents => #names.to_string(), )*
+                        _ => format!("`{}`", kind.0),
+                    }
+                }
+            }
+        });
+    }
+}
+
+pub fn lexer(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let lexer = match LexerInputReceiver::from_derive_input(&input) {
+        Ok(l) => l,
+        Err(e) => {
+            return e.write_errors().into();
+        }
+    };
+    quote!(#lexer).into()
+}
@@ -24,9 +24,6 @@ mod cst {
     enum Problem {
         #[display(fmt = "Expected either digit or one of `+`, `-`, `/`, `*`, `(`")]
         UnexpectedToken,
-
-        #[display(fmt = "Expected infix operator")]
-        UnexpectedInfix,
     }
 
     fn extra() -> std::sync::Arc<dyn Parser> {
@@ -42,21 +39,21 @@ mod cst {
     /// 2 ^ 3
     /// (2 ^ 3)
     /// (2 ^^^ 3)
+    /// 2 ^ 3 ^ 2
+    /// -2 ^ 2
+    /// 8 - 2 - 1
+    /// 2 % 3
     #[alder_test]
     pub fn value() -> impl Parser {
         with_extra(
             extra(),
-            pratt(Calc::Binary,
-                  vec![Calc::Value],
-                  |state: &mut State| left_value().parse(state),
-                  |state| match state.peek(1).as_ref() {
-                      "*" => Some((20, token("*").boxed())),
-                      "/" => Some((20, token("/").boxed())),
-                      "+" => Some((10, token("+").boxed())),
-                      "-" => Some((10, token("-").boxed())),
-                      "" | ")" => None,
-                      _ => Some((100, raise(Problem::UnexpectedInfix, 1).boxed())),
-            })
+            Pratt::new(left_value())
+                .alias(vec![Calc::Value])
+                .table(OperatorTable::new()
+                    .level(Assoc::Left, vec!["+", "-"]).node(Calc::Binary)
+                    .level(Assoc::Left, vec!["*", "/"]).node(Calc::Binary)
+                    .prefix(vec!["-"]).node(Calc::Unary)
+                    .level(Assoc::Right, vec!["^"]).node(Calc::Binary))
         )
     }
 
@@ -69,21 +66,11 @@ mod cst {
                     state.add(value());
                     state.add(")".as_extra());
                 },
-                "-" => {
-                    state.add(minus());
-                },
                 _ => state.add(raise(Problem::UnexpectedToken, 1)),
             };
         })
     }
 
-    fn minus() -> impl Parser {
-        node(Calc::Unary, |state| {
-            state.add("-");
-            state.add(value());
-        })
-    }
-
     fn number() -> impl Parser {
         recognize(Calc::Number, chomp_while(is_digits))
     }
@@ -95,6 +82,7 @@ mod ast {
     use crate::cst::Calc;
     use crate::*;
     use alder::{CstIterExt, FromCst, Node, NodeId, Span, State};
+    use std::convert::TryInto;
 
     #[derive(Debug)]
     pub enum Value {
@@ -219,6 +207,7 @@ mod ast {
                 BinOp::Sub => left - right,
                 BinOp::Div => left / right,
                 BinOp::Mul => left * right,
+                BinOp::Pow => left.checked_pow(right.try_into().ok()?)?,
             })
         }
     }
@@ -229,6 +218,7 @@ mod ast {
         Mul,
         Sub,
         Div,
+        Pow,
     }
 
     impl FromCst for BinOp {
@@ -241,6 +231,7 @@ mod ast {
                 "*" => Some(Self::Mul),
                 "-" => Some(Self::Sub),
                 "/" => Some(Self::Div),
+                "^" => Some(Self::Pow),
                 _ => None,
             }
         }
@@ -276,7 +267,10 @@ mod ast {
         #[test_case(r#"-5"#, "unary")]
         #[test_case(r#"-(1 + 2)"#, "unary_parent")]
         #[test_case(r#"-(1 + err)"#, "error")]
+        #[test_case(r#"(1 + err) * 3 + 4"#, "error3")]
         #[test_case(r#"1 + 1 ^ 2"#, "error2")]
+        #[test_case(r#"2 ^ 3 ^ 2"#, "power")]
+        #[test_case(r#"-2 + 3"#, "unary_preced")]
         fn expr_test(input: &str, test_case_name: &str) {
             let cst = State::parse(input, crate::cst::value());
             let mut nodes = cst.nodes.iter();
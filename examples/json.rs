@@ -21,6 +21,7 @@ mod cst {
             String,
             Value,
             Boolean,
+            Number,
             Array,
             Object,
 
@@ -30,6 +31,7 @@ mod cst {
 
     use derive_more::Display;
     #[derive(Debug, Display, Clone)]
+    #[allow(clippy::enum_variant_names)]
     enum Problem {
         #[display(fmt = "I expected `true` or `false`")]
         InvalidBoolean,
@@ -40,12 +42,9 @@ mod cst {
         #[display(fmt = "I expected `,` or `}}`")]
         InvalidTokenObject,
 
-        #[display(fmt = "I expected `true`, `false`, `[`, `{{` or `\"`")]
+        #[display(fmt = "I expected `true`, `false`, `[`, `{{`, `\"` or a number")]
         InvalidTokenValue,
 
-        #[display(fmt = "I expected `*/`")]
-        UnexpectedEOFComment,
-
         #[display(fmt = "I expected `//` or `/*`")]
         InvalidTokenComment,
     }
@@ -79,6 +78,7 @@ mod cst {
         baz */
     */
     /// /* foo
+    /// /* a /* b */ c */
     #[alder_test]
     fn comment() -> impl Parser {
         v_node(Json::Comment,
@@ -91,20 +91,8 @@ mod cst {
     }
 
     fn multiline_comment() -> impl Parser {
-        node(Json::MultilineComment, |state| loop {
-            match state.peek(2).as_ref() {
-                "" => {
-                    state.add(raise(Problem::UnexpectedEOFComment, 0));
-                    break;
-                },
-                "*/" => {
-                    state.chomp(2);
-                    break;
-                },
-                _ => {
-                    state.chomp(1);
-                }
-            }
+        v_node(Json::MultilineComment, |state| {
+            state.add(block_comment("/*", "*/", true));
         })
     }
 
@@ -144,6 +132,7 @@ mod cst {
         [true, / invalid comment
         false]
     */
+    /// [1, -2.5e3]
     #[alder_test]
     pub fn value() -> impl Parser {
         with_extra(
@@ -154,6 +143,7 @@ mod cst {
                     "[" => state.add(array()),
                     "{" => state.add(object()),
                     "\"" => state.add(string()),
+                    s if s == "-" || s.is_digits() => state.add(number()),
                     _ => state.add(raise(Problem::InvalidTokenValue, 1)),
                 };
             }),
@@ -167,13 +157,13 @@ mod cst {
         "foo
         bar"
     */
+    /// "a\"b\u00e9"
+    /// "a\qb"
+    /// "é"́
     #[alder_test]
     pub fn string() -> impl Parser {
-        no_extra(node(Json::String, |state| {
-            state.add("\"");
-            state.add(recognize(Json::Value,chomp_until( |c| c == "\"" || c.is_line_ending())));
-            state.add("\"");
-        }))
+        // JSON strings are made of code points, a quote followed by a combining mark still ends them.
+        with_unit(Unit::Char, string_literal(StringFormat::json()))
     }
 
     /// true
@@ -183,12 +173,24 @@ mod cst {
     #[alder_test]
     fn boolean() -> impl Parser {
         v_node(Json::Boolean, |state| match state.peek(1).as_ref() {
-            "t" => state.add("true"),
-            "f" => state.add("false"),
+            "t" => state.add(keyword("true")),
+            "f" => state.add(keyword("false")),
             _ => state.add(raise(Problem::InvalidBoolean, 1)),
         })
     }
 
+    /// 0
+    /// -12.5e3
+    /// 007
+    /// 1.
+    /// 1e
+    #[alder_test]
+    fn number() -> impl Parser {
+        v_node(Json::Number, |state| {
+            state.add(alder::number(NumberFormat::json()));
+        })
+    }
+
     /// {}
     /// {"foo":"bar"}
     /// { "foo": "bar" }
@@ -203,36 +205,12 @@ mod cst {
         with_extra(
             extra(),
             node(Json::Object, |state| {
-                state.add("{");
-                match state.peek(1).as_ref() {
-                    "}" => (),
-                    _ => 'outer: loop {
-                        state.add(field(Json::Key, string()));
-                        state.add(recover(":"));
-                        state.add(value());
-                        'inner: loop {
-                            match state.peek(1).as_ref() {
-                                "}" => {
-                                    break 'outer;
-                                }
-                                "," => {
-                                    state.add(recover(","));
-                                    if let "}" = state.peek(1).as_ref() {
-                                        // Trailing comma
-                                        break 'outer;
-                                    }
-                                    break 'inner;
-                                }
-                                "" => { // EOF
-                                    state.add(raise(Problem::InvalidTokenObject, 1));
-                                    break 'outer;
-                                }
-                                _ => state.add(raise(Problem::InvalidTokenObject, 1)),
-                            };
-                        }
-                    },
-                };
-                state.add(recover("}"));
+                let pair = v_node(None, |state| {
+                    state.add(field(Json::Key, string()));
+                    state.add(recover(":"));
+                    state.add(value());
+                });
+                state.add(delimited_list("{", pair, ",", "}", Problem::InvalidTokenObject));
             }),
         )
     }
@@ -253,35 +231,7 @@ mod cst {
         with_extra(
             extra(),
             node(Json::Array, |state| {
-                state.add("[");
-                match state.peek(1).as_ref() {
-                    "]" => (),
-                    _ => 'outer: loop {
-                        state.add(value());
-                        'inner: loop {
-                            // Until we find either ']' or ','
-                            match state.peek(1).as_ref() {
-                                "]" => {
-                                    break 'outer;
-                                }
-                                "," => {
-                                    state.add(recover(","));
-                                    if let "]" = state.peek(1).as_ref() {
-                                        // Trailing comma
-                                        break 'outer;
-                                    }
-                                    break 'inner;
-                                }
-                                "" => { // EOF
-                                    state.add(raise(Problem::InvalidTokenArray, 1));
-                                    break 'outer;
-                                }
-                                _ => state.add(raise(Problem::InvalidTokenArray, 1)),
-                            };
-                        }
-                    },
-                }
-                state.add(recover("]"));
+                state.add(delimited_list("[", value(), ",", "]", Problem::InvalidTokenArray));
             }),
         )
     }
@@ -289,7 +239,7 @@ mod cst {
 
 mod ast {
     use crate::*;
-    use alder::{Ast, Node, State};
+    use alder::{unescape, Ast, Node, State, Str, StringFormat};
     #[cfg(not(feature = "derive"))]
     use alder_derive::Ast;
 
@@ -354,13 +304,16 @@ mod ast {
     }
 
     impl String {
-        fn value(&self) -> &str {
-            self.node
+        fn value(&self) -> std::string::String {
+            let text = self
+                .node
                 .children
                 .iter()
-                .find(|c| c.is(cst::Json::Value))
-                .map(|value| value.span.as_ref())
-                .unwrap_or_default()
+                .filter(|c| c.is(Str::Text) || c.is(Str::Escape))
+                .map(|c| c.span.as_ref())
+                .collect::<Vec<_>>()
+                .concat();
+            unescape(&StringFormat::json(), &text)
         }
     }
 
@@ -398,7 +351,7 @@ mod ast {
     }
 
     impl Object {
-        fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        fn iter(&self) -> impl Iterator<Item = (std::string::String, &Value)> {
             self.pairs
                 .iter()
                 .map(|KeyValuePair { key, value }| (key.value(), value))
@@ -422,6 +375,10 @@ mod ast {
             let value = Value::from_str(r#""true""#).unwrap();
             let v = value.as_string();
             assert_eq!("true", v.value());
+
+            let value = Value::from_str(r#""a\"b\u00e9""#).unwrap();
+            let v = value.as_string();
+            assert_eq!("a\"b\u{e9}", v.value());
         }
 
         #[test]
@@ -457,7 +414,7 @@ mod ast {
                 .map(|(k, v)| (k, v.as_boolean().value()))
                 .collect::<Vec<_>>();
 
-            assert_eq!(values, vec![("a", true), ("b", false)]);
+            assert_eq!(values, vec![("a".into(), true), ("b".into(), false)]);
         }
 
         #[test]
@@ -468,7 +425,11 @@ mod ast {
 
             assert_eq!(
                 values,
-                vec![("a", "boolean"), ("c", "array"), ("b", "boolean")]
+                vec![
+                    ("a".into(), "boolean"),
+                    ("c".into(), "array"),
+                    ("b".into(), "boolean")
+                ]
             );
         }
 
@@ -480,7 +441,11 @@ mod ast {
 
             assert_eq!(
                 values,
-                vec![("a", "boolean"), ("", "array"), ("b", "boolean")]
+                vec![
+                    ("a".into(), "boolean"),
+                    ("".into(), "array"),
+                    ("b".into(), "boolean")
+                ]
             );
         }
     }
@@ -0,0 +1,132 @@
+#![allow(dead_code)]
+
+// Template strings like `"Hello ${user.name}!"` switch between two lexers:
+// one for expressions and one for the string content.
+#[rustfmt::skip]
+mod cst {
+    use alder::*;
+    use std::sync::Arc;
+
+    #[cfg(not(feature = "derive"))]
+    use alder_derive::{alder_test, Lexer};
+
+    // Tokens of expressions. Variants without rules are just node names.
+    #[derive(Lexer)]
+    #[lexer(ids = "Expr")]
+    enum ExprToken {
+        #[token("\"")]
+        Quote,
+        #[token("+")]
+        Plus,
+        #[token(".")]
+        Dot,
+        #[token("}")]
+        RBrace,
+        #[regex("[0-9]+", name = "a number")]
+        Number,
+        #[regex("[A-Za-z_][A-Za-z0-9_]*", name = "an identifier")]
+        Ident,
+        #[trivia]
+        #[regex(r"\s+")]
+        WS,
+
+        // Nodes
+        Expression,
+        Path,
+        Template,
+        Interpolation,
+    }
+
+    // Tokens inside of a template string. Whitespace is a part of the text here.
+    #[derive(Lexer)]
+    #[lexer(ids = "Str")]
+    enum StrToken {
+        #[token("\"")]
+        Quote,
+        #[token("${")]
+        InterpolationStart,
+        #[regex(r"\\.", name = "an escape")]
+        Escape,
+        #[regex(r#"(?:[^"\\$]|\$[^{"\\$])+|\$"#, name = "a text")]
+        Text,
+    }
+
+    fn expr_mode() -> Arc<dyn Lexer> {
+        Arc::new(Expr)
+    }
+
+    fn string_mode() -> Arc<dyn Lexer> {
+        Arc::new(Str)
+    }
+
+    /// 1 + 2
+    /// user.name
+    /// "Hello ${user.name}!"
+    /// "Hello ${ user.first + " " + user.last }!"
+    /// "a ${ "b ${ "c ${d}" }" } e"
+    /// "${1}${2}"
+    /// "cost: $5 \" ${x"
+    /// "a ${ } b"
+    /// "a ${b"
+    #[alder_test]
+    pub fn value() -> impl Parser {
+        with_mode(expr_mode(), expression())
+    }
+
+    fn expression() -> impl Parser {
+        node(Expr::Expression, |state| {
+            state.add(atom());
+            while state.peek_kind() == Some(Expr::Plus) {
+                state.add(kind(Expr::Plus));
+                state.add(atom());
+            }
+        })
+    }
+
+    fn atom() -> impl Parser {
+        v_node(None, |state| match state.peek_kind() {
+            Some(Expr::Quote) => state.add(template()),
+            Some(Expr::Number) => state.add(kind(Expr::Number)),
+            _ => state.add(path()),
+        })
+    }
+
+    fn path() -> impl Parser {
+        node(Expr::Path, |state| {
+            state.add(kind(Expr::Ident));
+            while state.peek_kind() == Some(Expr::Dot) {
+                state.add(kind(Expr::Dot));
+                state.add(kind(Expr::Ident));
+            }
+        })
+    }
+
+    fn template() -> impl Parser {
+        with_mode(string_mode(), node(Expr::Template, |state| {
+            state.add(kind(Str::Quote));
+            loop {
+                match state.peek_kind() {
+                    Some(Str::Text) => state.add(kind(Str::Text)),
+                    Some(Str::Escape) => state.add(kind(Str::Escape)),
+                    Some(Str::InterpolationStart) => state.add(interpolation()),
+                    _ => break,
+                }
+            }
+            state.add(recover(kind(Str::Quote)));
+        }))
+    }
+
+    fn interpolation() -> impl Parser {
+        node(Expr::Interpolation, |state| {
+            state.add(kind(Str::InterpolationStart));
+            state.add(with_mode(expr_mode(), v_node(None, |state| {
+                state.add(expression());
+                // No trailing extra, text after `}` belongs to the string again.
+                let close = recover(kind(Expr::RBrace)).parse(state);
+                state.add_node(close);
+            })));
+        })
+    }
+}
+
+fn main() {}
@@ -0,0 +1,124 @@
+use crate::*;
+use unicode_segmentation::GraphemeCursor;
+
+/// Iterator over extended graphemes and their byte offsets, like `grapheme_indices`.
+/// ASCII is stepped without segmentation, only the boundary after a non-ASCII byte
+/// is found with `GraphemeCursor`, so walking the whole input is linear.
+///
+/// With `Unit::Char` it steps over `char`s instead, `\r\n` is still one step.
+/// With a lexer it steps over its tokens, and over `Unit`s where no token matches.
+#[derive(Clone)]
+pub struct Cursor<'a> {
+    text: &'a str,
+    offset: usize,
+    unit: Unit,
+    lexer: Option<&'a dyn Lexer>,
+}
+
+impl<'a> std::fmt::Debug for Cursor<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cursor")
+            .field("text", &self.text)
+            .field("offset", &self.offset)
+            .field("unit", &self.unit)
+            .field("lexer", &self.lexer.is_some())
+            .finish()
+    }
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self::with_unit(text, Unit::Grapheme)
+    }
+
+    pub fn with_unit(text: &'a str, unit: Unit) -> Self {
+        Self {
+            text,
+            offset: 0,
+            unit,
+            lexer: None,
+        }
+    }
+
+    /// Steps over tokens of `lexer`, see `Parse::lexer`.
+    pub fn with_lexer(mut self, lexer: &'a dyn Lexer) -> Self {
+        self.lexer = Some(lexer);
+        self
+    }
+
+    /// Byte offset of the next step.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn next_boundary(&self) -> usize {
+        let bytes = self.text.as_bytes();
+        let offset = self.offset;
+        if let Some(lexer) = self.lexer {
+            match lexer.lex(&self.text[offset..]) {
+                Some((_, len)) if len > 0 => return (offset + len).min(self.text.len()),
+                _ => (),
+            }
+        }
+        match (bytes[offset], bytes.get(offset + 1)) {
+            (b'\r', Some(b'\n')) => return offset + 2,
+            _ if self.unit == Unit::Char => {
+                let current = self.text[offset..].chars().next();
+                return offset + current.map(char::len_utf8).unwrap_or_default();
+            }
+            (current, Some(next)) if current.is_ascii() && next.is_ascii() => return offset + 1,
+            (current, None) if current.is_ascii() => return offset + 1,
+            _ => (),
+        }
+        GraphemeCursor::new(offset, self.text.len(), true)
+            .next_boundary(self.text, 0)
+            .ok()
+            .and_then(|boundary| boundary)
+            .unwrap_or(self.text.len())
+    }
+}
+
+impl<'a> Iterator for Cursor<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.text.len() {
+            return None;
+        }
+        let start = self.offset;
+        self.offset = self.next_boundary();
+        Some((start, &self.text[start..self.offset]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+    use unicode_segmentation::UnicodeSegmentation;
+
+    #[test_case("" ; "empty")]
+    #[test_case("foo bar" ; "ascii")]
+    #[test_case("a\r\nb\n\r" ; "line endings")]
+    #[test_case("a\u{310}e\u{301}o\u{308}\u{332}\r\n" ; "combining")]
+    #[test_case("\"\u{301}x" ; "combining after ascii")]
+    #[test_case("\u{1F1F5}\u{1F1F1}\u{1F1F5}a" ; "regional indicators")]
+    #[test_case("\u{1F469}\u{200D}\u{1F4BB}!" ; "zwj sequence")]
+    #[test_case("\u{600}1 x" ; "prepend")]
+    fn same_as_grapheme_indices(input: &str) {
+        let expected = input.grapheme_indices(true).collect::<Vec<_>>();
+        let actual = Cursor::new(input).collect::<Vec<_>>();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test_case(Unit::Grapheme, &["e\u{301}", "\u{e9}", "\r\n"])]
+    #[test_case(Unit::Char, &["e", "\u{301}", "\u{e9}", "\r\n"])]
+    fn units(unit: Unit, expected: &[&str]) {
+        let actual = Cursor::with_unit("e\u{301}\u{e9}\r\n", unit)
+            .map(|(_, step)| step)
+            .collect::<Vec<_>>();
+
+        assert_eq!(actual, expected);
+    }
+}
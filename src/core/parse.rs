@@ -0,0 +1,261 @@
+use crate::*;
+use std::any::Any;
+use std::sync::Arc;
+
+/// How tokens and keywords are compared with the input.
+/// Matched nodes always keep the original source text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Matching {
+    /// `SELECT` matches `select` and `Select`.
+    pub case_insensitive: bool,
+    /// Compare NFC forms, so decomposed input matches composed tokens.
+    pub normalized: bool,
+}
+
+/// What `peek`, `chomp`, `chomp_while` and other utf parsers step over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// Extended grapheme clusters, so `e\u{301}` is one step.
+    Grapheme,
+    /// Unicode scalar values, for formats defined in code points like JSON.
+    /// `\r\n` is still one step, so it stays a single line ending.
+    Char,
+}
+
+// `#[default]` on enum variants needs a newer compiler than the pinned one.
+impl Default for Unit {
+    fn default() -> Self {
+        Unit::Grapheme
+    }
+}
+
+impl Unit {
+    /// Number of steps in `text`, e.g. to `peek` a whole token.
+    pub fn count(self, text: &str) -> usize {
+        Cursor::with_unit(text, self).count()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Maximum number of nested nodes before the parser gives up descending.
+    pub max_depth: Option<usize>,
+    /// Maximum number of reported errors. Further problems still produce `ERROR` nodes.
+    pub max_errors: Option<usize>,
+    /// Print every entered and finished node to stderr.
+    pub trace: bool,
+    /// Report leftover input as an error, see `State::parse_complete`.
+    pub complete: bool,
+    /// Name of the parsed source, e.g. a file path, used in diagnostics.
+    pub source_name: Option<String>,
+    /// Used by `token`, `keyword` and `&'static str` parsers.
+    pub matching: Matching,
+    /// Can be changed for a part of the input with `with_unit`.
+    pub unit: Unit,
+}
+
+pub struct Parse<P: Parser> {
+    parser: P,
+    options: ParseOptions,
+    extra: Option<Arc<dyn Parser>>,
+    context: Option<Box<dyn Any>>,
+    lexer: Option<Arc<dyn Lexer>>,
+}
+
+impl<P: Parser> Parse<P> {
+    pub fn new(parser: P) -> Self {
+        Self {
+            parser,
+            options: ParseOptions::default(),
+            extra: None,
+            context: None,
+            lexer: None,
+        }
+    }
+
+    pub fn options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Extra parser used around the root parser and for trailing input.
+    pub fn extra(mut self, extra: Arc<dyn Parser>) -> Self {
+        self.extra = Some(extra);
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.options.max_errors = Some(max_errors);
+        self
+    }
+
+    pub fn trace(mut self, trace: bool) -> Self {
+        self.options.trace = trace;
+        self
+    }
+
+    pub fn complete(mut self, complete: bool) -> Self {
+        self.options.complete = complete;
+        self
+    }
+
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.options.matching.case_insensitive = case_insensitive;
+        self
+    }
+
+    pub fn normalized(mut self, normalized: bool) -> Self {
+        self.options.matching.normalized = normalized;
+        self
+    }
+
+    pub fn unit(mut self, unit: Unit) -> Self {
+        self.options.unit = unit;
+        self
+    }
+
+    pub fn source_name(mut self, source_name: impl Into<String>) -> Self {
+        self.options.source_name = Some(source_name.into());
+        self
+    }
+
+    /// Parses on top of tokens produced by `lexer`, see `kind` and `State::peek_token`.
+    /// Trivia tokens are attached as extras unless `extra` is set.
+    pub fn lexer(mut self, lexer: impl Lexer + 'static) -> Self {
+        self.lexer = Some(Arc::new(lexer));
+        self
+    }
+
+    /// Any value parsers can later access with `State::user_context`.
+    pub fn user_context<T: Any>(mut self, context: T) -> Self {
+        self.context = Some(Box::new(context));
+        self
+    }
+
+    pub fn run(self, input: &str) -> Parsed {
+        let mut state = State::from(input);
+        state.options = self.options;
+        state.user_context = self.context;
+
+        if let Some(lexer) = self.lexer {
+            state.tokens = Some(TokenStream::new(lexer));
+        }
+        match self.extra {
+            Some(extra) => state.push_extra(extra),
+            None if state.tokens.is_some() => state.push_extra(trivia().arc()),
+            None => (),
+        }
+        state.add(self.parser);
+        if state.options.complete {
+            state.add_eof();
+        }
+        state.into_parsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parens() -> impl Parser {
+        node(NodeId("Parens"), |state| {
+            state.add("(");
+            if let "(" = state.peek(1).as_ref() {
+                state.add(parens());
+            }
+            state.add(recover(")"));
+        })
+    }
+
+    #[test]
+    fn defaults() {
+        let parsed = Parse::new(parens()).run("(())x");
+
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.rest.as_ref(), "x");
+        assert_eq!(parsed.source_name, None);
+    }
+
+    #[test]
+    fn max_depth() {
+        let parsed = Parse::new(parens()).max_depth(3).run("((((()))))");
+
+        assert_eq!(parsed.errors[0].span.range.0, 3);
+        assert_eq!(
+            parsed.errors[0].problem.to_string(),
+            "I reached the maximum nesting depth of 3"
+        );
+    }
+
+    #[test]
+    fn max_errors() {
+        let parser = node(NodeId("List"), |state| {
+            while !state.peek(1).is_empty() {
+                state.add(recover("a"));
+            }
+        });
+        let parsed = Parse::new(parser).max_errors(2).run("abababa");
+
+        assert_eq!(parsed.errors.len(), 2);
+    }
+
+    #[test]
+    fn max_errors_keeps_reported_span() {
+        let parser = node(NodeId("List"), |state| {
+            while !state.peek(1).is_empty() {
+                state.add(recover("a"));
+            }
+        });
+        let parsed = Parse::new(parser).max_errors(1).run("xaxx");
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].span.as_ref(), "x");
+    }
+
+    #[test]
+    fn complete() {
+        let extra = recognize(NodeId("WS"), chomp_while(is_ws)).arc();
+        let parsed = Parse::new(parens())
+            .extra(extra)
+            .complete(true)
+            .source_name("input.txt")
+            .run(" () x");
+
+        assert_eq!(parsed.source_name.as_deref(), Some("input.txt"));
+        assert!(parsed.rest.is_empty());
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].span.as_ref(), "x");
+    }
+
+    #[test]
+    fn matching() {
+        let parser = || node(NodeId("Select"), |state| state.add("select"));
+        let parsed = Parse::new(parser())
+            .case_insensitive(true)
+            .normalized(true)
+            .run("SeLeCt");
+
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.nodes[0].span.as_ref(), "SeLeCt");
+
+        let parsed = Parse::new(parser()).run("SeLeCt");
+        assert_eq!(parsed.errors.len(), 1);
+    }
+
+    #[test]
+    fn user_context() {
+        let parser = |state: &mut State| {
+            let expected = *state.user_context::<&str>().expect("Context");
+            token(expected).parse(state)
+        };
+        let parsed = Parse::new(parser).user_context("foo").run("foo");
+
+        assert!(parsed.errors.is_empty());
+        assert!(parsed.rest.is_empty());
+    }
+}
@@ -11,4 +11,146 @@ pub struct Parsed {
     pub rest: Span,
     pub nodes: Vec<Node>,
     pub errors: Vec<ParseError>,
+    pub source_name: Option<String>,
+    pub(crate) root: Node,
+}
+
+impl Parsed {
+    pub(crate) fn new(
+        input: Span,
+        rest: Span,
+        nodes: Vec<Node>,
+        errors: Vec<ParseError>,
+        source_name: Option<String>,
+    ) -> Self {
+        let root = Node {
+            children: clone_nodes(&nodes),
+            ..Node::root(input.clone())
+        };
+        Self {
+            input,
+            rest,
+            nodes,
+            errors,
+            source_name,
+            root,
+        }
+    }
+
+    /// `ROOT` node covering the whole input, with the parsed `nodes` as children.
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    pub fn into_result(self) -> Result<Tree, Diagnostics> {
+        if self.errors.is_empty() {
+            Ok(Tree {
+                input: self.input,
+                root: self.root,
+            })
+        } else {
+            Err(Diagnostics {
+                input: self.input,
+                errors: self.errors,
+                source_name: self.source_name,
+            })
+        }
+    }
+}
+
+/// Clones nodes without recursion, so trees as deep as left recursion
+/// can grow them don't overflow the stack.
+fn clone_nodes(nodes: &[Node]) -> Vec<Node> {
+    struct Frame<'a> {
+        node: Option<&'a Node>,
+        children: std::slice::Iter<'a, Node>,
+        cloned: Vec<Node>,
+    }
+
+    let mut stack = vec![Frame {
+        node: None,
+        children: nodes.iter(),
+        cloned: Vec::with_capacity(nodes.len()),
+    }];
+    loop {
+        let top = stack.last_mut().expect("At least top frame");
+        if let Some(child) = top.children.next() {
+            stack.push(Frame {
+                node: Some(child),
+                children: child.children.iter(),
+                cloned: Vec::with_capacity(child.children.len()),
+            });
+            continue;
+        }
+        let frame = stack.pop().expect("At least top frame");
+        match (frame.node, stack.last_mut()) {
+            (Some(node), Some(parent)) => parent.cloned.push(Node {
+                name: node.name,
+                alias: node.alias.clone(),
+                span: node.span.clone(),
+                children: frame.cloned,
+            }),
+            _ => return frame.cloned,
+        }
+    }
+}
+
+/// Syntax tree of an input parsed without any errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tree {
+    pub input: Span,
+    pub root: Node,
+}
+
+impl Tree {
+    pub fn nodes(&self) -> &[Node] {
+        &self.root.children
+    }
+}
+
+/// All errors found while parsing an input.
+#[derive(Debug)]
+pub struct Diagnostics {
+    pub input: Span,
+    pub errors: Vec<ParseError>,
+    pub source_name: Option<String>,
+}
+
+impl std::error::Error for Diagnostics {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn root_covers_input() {
+        let parsed = State::parse("truex", token("true"));
+
+        let root = parsed.root();
+
+        assert!(root.is(NodeId::ROOT));
+        assert_eq!(root.span.as_ref(), "truex");
+        assert_eq!(root.children, parsed.nodes);
+    }
+
+    #[test]
+    fn into_result_ok() {
+        let tree = State::parse("true", token("true"))
+            .into_result()
+            .expect("Tree");
+
+        assert_eq!(tree.nodes()[0].span.as_ref(), "true");
+    }
+
+    #[test]
+    fn into_result_err() {
+        let diagnostics = State::parse("false", token("true"))
+            .into_result()
+            .expect_err("Diagnostics");
+
+        assert_eq!(diagnostics.errors.len(), 1);
+        let rendered = diagnostics.to_string();
+        assert!(rendered.contains("SYNTAX ERROR"));
+        assert!(rendered.contains("^^^^ I expected `true`"));
+    }
 }
@@ -48,7 +48,7 @@ impl AsRef<str> for Span {
 
 use unicode_segmentation::{GraphemeIndices, UnicodeSegmentation};
 impl Span {
-    pub fn graphemes_idx(&self) -> GraphemeIndices {
+    pub fn graphemes_idx(&self) -> GraphemeIndices<'_> {
         self.as_ref().grapheme_indices(true)
     }
 }
@@ -1,7 +1,19 @@
 use crate::*;
+use derive_more::Display;
+use std::any::Any;
 use std::fmt::{Debug, Error, Formatter};
+use std::rc::Rc;
 use std::sync::Arc;
 
+#[derive(Debug, Display, Clone)]
+pub(crate) enum StateError {
+    #[display(fmt = "I expected end of input")]
+    ExpectedEOF,
+
+    #[display(fmt = "I reached the maximum nesting depth of {}", _0)]
+    MaxDepth(usize),
+}
+
 #[derive(Debug)]
 pub struct ParseErrorContext {
     pub node: NodeId,
@@ -35,13 +47,49 @@ impl ParseError {
     }
 }
 
+/// Why a text parser failed, raised by `recognize` when nothing was recognized.
+/// `context` holds names given with `context` on the way up, innermost first.
+#[derive(Debug, Clone)]
+pub(crate) struct Failure {
+    pub(crate) problem: Rc<dyn Problem>,
+    pub(crate) context: Vec<NodeId>,
+}
+
+/// Snapshot of the `State` used to backtrack, see `State::rewind`.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    input: Span,
+    errors: usize,
+    panic: bool,
+    panic_error: Option<usize>,
+    depth: usize,
+    children: usize,
+    indentation: Indentation,
+    modes: Option<Vec<Mode>>,
+    active_rules: Vec<(&'static str, usize)>,
+    failure: Option<Failure>,
+}
+
 pub struct State {
     pub input: Span,
     pub nodes: Vec<Node>,
     extras: Vec<Option<Arc<dyn Parser>>>,
+    top_extra: Option<Arc<dyn Parser>>,
     parsing_extra: bool,
     pub(crate) errors: Vec<ParseError>,
     pub panic: bool,
+    /// Index of the error reported when the current panic started,
+    /// `None` once `max_errors` was reached.
+    pub(crate) panic_error: Option<usize>,
+    pub(crate) options: ParseOptions,
+    pub(crate) user_context: Option<Box<dyn Any>>,
+    pub(crate) indentation: Indentation,
+    pub(crate) tokens: Option<TokenStream>,
+    pub(crate) grammar: Option<Arc<Definitions>>,
+    pub(crate) active_rules: Vec<(&'static str, usize)>,
+    /// Rules found calling themselves without consuming input, see `Grammar::check`.
+    pub(crate) cycles: Vec<&'static str>,
+    pub(crate) failure: Option<Failure>,
 }
 
 impl Debug for State {
@@ -59,23 +107,90 @@ impl<'a> From<&'a str> for State {
             input: input.clone(),
             nodes: vec![Node::root(input)],
             extras: vec![],
+            top_extra: None,
             errors: vec![],
             parsing_extra: false,
             panic: false,
+            panic_error: None,
+            options: ParseOptions::default(),
+            user_context: None,
+            indentation: Indentation::default(),
+            tokens: None,
+            grammar: None,
+            active_rules: vec![],
+            cycles: vec![],
+            failure: None,
         }
     }
 }
 
 impl State {
+    /// Runs the parser with default options. Use `Parse` to configure the run.
     pub fn parse(input: &str, parser: impl Parser) -> Parsed {
-        let mut state = Self::from(input);
-        state.add(parser);
-        let nodes = state.nodes.pop().expect("At least root").children;
-        Parsed {
-            input: input.into(),
-            rest: state.input,
+        Parse::new(parser).run(input)
+    }
+
+    /// Like `parse`, but leftover input is reported as an error instead of
+    /// being returned in `Parsed::rest`.
+    /// Trailing extras are consumed first using the top-level extra parser.
+    pub fn parse_complete(input: &str, parser: impl Parser) -> Parsed {
+        Parse::new(parser).complete(true).run(input)
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Steps over the rest of input in `Unit` from options,
+    /// or over tokens while a lexer is active, see `Parse::lexer`.
+    pub fn cursor(&self) -> Cursor<'_> {
+        self.steps_of(self.input.as_ref())
+    }
+
+    /// Number of steps `text` takes when stepped over like the input, e.g. to `peek` a whole token.
+    pub fn steps(&self, text: &str) -> usize {
+        self.steps_of(text).count()
+    }
+
+    fn steps_of<'a>(&'a self, text: &'a str) -> Cursor<'a> {
+        let cursor = Cursor::with_unit(text, self.options.unit);
+        match self.tokens.as_ref() {
+            Some(stream) => cursor.with_lexer(stream.lexer()),
+            None => cursor,
+        }
+    }
+
+    pub fn user_context<T: Any>(&self) -> Option<&T> {
+        self.user_context.as_ref().and_then(|c| c.downcast_ref())
+    }
+
+    pub fn user_context_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.user_context.as_mut().and_then(|c| c.downcast_mut())
+    }
+
+    pub(crate) fn into_parsed(mut self) -> Parsed {
+        let nodes = self.nodes.pop().expect("At least root").children;
+        Parsed::new(
+            self.input.full(),
+            self.input,
             nodes,
-            errors: state.errors,
+            self.errors,
+            self.options.source_name,
+        )
+    }
+
+    pub(crate) fn add_eof(&mut self) {
+        if let Some(extra) = self.top_extra.clone() {
+            self.extras.push(Some(extra));
+            if let Some(extra) = self.add_extra() {
+                self.add_node_inner(extra);
+            }
+            self.extras.pop();
+        }
+
+        if !self.input.is_empty() {
+            let len = self.input.len();
+            self.add(raise(StateError::ExpectedEOF, len));
         }
     }
 
@@ -107,7 +222,50 @@ impl State {
 }
 
 impl State {
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            input: self.input.clone(),
+            errors: self.errors.len(),
+            panic: self.panic,
+            panic_error: self.panic_error,
+            depth: self.nodes.len(),
+            children: self
+                .nodes
+                .last()
+                .map(|n| n.children.len())
+                .unwrap_or_default(),
+            indentation: self.indentation.clone(),
+            modes: self.modes(),
+            active_rules: self.active_rules.clone(),
+            failure: self.failure.clone(),
+        }
+    }
+
+    /// Restores input, errors, nodes, lexer modes, active grammar rules
+    /// and the failure of text parsers changed since the checkpoint was taken.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.input = checkpoint.input;
+        self.failure = checkpoint.failure;
+        self.restore_modes(checkpoint.modes);
+        self.active_rules = checkpoint.active_rules;
+        self.errors.truncate(checkpoint.errors);
+        self.panic = checkpoint.panic;
+        self.panic_error = checkpoint.panic_error;
+        self.indentation = checkpoint.indentation;
+        self.nodes.truncate(checkpoint.depth);
+        if let Some(node) = self.nodes.last_mut() {
+            node.children.truncate(checkpoint.children);
+        }
+    }
+}
+
+impl State {
+    /// The first extra pushed, by `Parse::extra` or the root parser,
+    /// is kept for trailing input, see `State::parse_complete`.
     pub fn push_extra(&mut self, extra: std::sync::Arc<dyn Parser>) {
+        if self.top_extra.is_none() && self.extras.is_empty() {
+            self.top_extra = Some(extra.clone());
+        }
         self.extras.push(Some(extra));
     }
 
@@ -174,3 +332,67 @@ impl State {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root() -> impl Parser {
+        let extra = recognize(NodeId("WS"), chomp_while(is_ws)).arc();
+        with_extra(extra, token("true"))
+    }
+
+    #[test]
+    fn parse_returns_rest() {
+        let parsed = State::parse("true x", root());
+
+        assert_eq!(parsed.rest.as_ref(), " x");
+        assert!(parsed.errors.is_empty());
+    }
+
+    #[test]
+    fn parse_complete_consumes_trailing_extra() {
+        let parsed = State::parse_complete("true  ", root());
+
+        assert!(parsed.rest.is_empty());
+        assert!(parsed.errors.is_empty());
+        let last = parsed.nodes.last().expect("Extra");
+        assert!(last.is(NodeId::EXTRA));
+        assert_eq!(last.span.as_ref(), "  ");
+    }
+
+    #[test]
+    fn parse_complete_reports_rest() {
+        let parsed = State::parse_complete("true x y", root());
+
+        assert!(parsed.rest.is_empty());
+        assert_eq!(parsed.errors.len(), 1);
+        let error = &parsed.errors[0];
+        assert_eq!(error.span.as_ref(), "x y");
+        assert_eq!(error.problem.to_string(), "I expected end of input");
+        assert!(parsed.nodes.last().expect("Error").is(NodeId::ERROR));
+    }
+
+    #[test]
+    fn parse_complete_uses_first_extra() {
+        let dashes = recognize(NodeId("Dashes"), chomp_while(|c: &str| c == "-")).arc();
+        let parser = v_node(None, move |state| {
+            state.add(root());
+            state.add(with_extra(dashes.clone(), v_node(None, |state| state.add("x"))));
+        });
+        let parsed = State::parse_complete("truex--  ", parser);
+
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.nodes.last().expect("Extra").span.as_ref(), "  ");
+    }
+
+    #[test]
+    fn rewind_restores_active_rules() {
+        let mut state: State = "x".into();
+        let checkpoint = state.checkpoint();
+        state.active_rules.push(("value", 0));
+        state.rewind(checkpoint);
+
+        assert!(state.active_rules.is_empty());
+    }
+}
@@ -0,0 +1,638 @@
+use crate::*;
+use derive_more::Display;
+use std::sync::Arc;
+
+#[derive(Debug, Display, Clone)]
+enum TokenError {
+    #[display(fmt = "I expected {}", _0)]
+    Expected(String),
+
+    #[display(fmt = "I expected `{}` token, but there is no lexer, see `Parse::lexer`", _0)]
+    NoLexer(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: NodeId,
+    pub span: Span,
+}
+
+/// Splits input into tokens for the token-stream mode, see `Parse::lexer`.
+///
+/// Tokens are read with `kind`, `State::peek_token`, `State::peek_kind` and `State::chomp_token`.
+/// Text parsers like `peek`, `chomp` or `&str` step over whole tokens, trivia included,
+/// so `"+"` doesn't match the beginning of a `+=` token.
+pub trait Lexer {
+    /// Kind and length in bytes of the token at the beginning of `input`,
+    /// or `None` when no token matches.
+    fn lex(&self, input: &str) -> Option<(NodeId, usize)>;
+
+    /// Trivia, like whitespace or comments, is attached to the tree as extras.
+    fn is_trivia(&self, _kind: NodeId) -> bool {
+        false
+    }
+
+    /// Name of the token used in "I expected ..." messages.
+    fn describe(&self, kind: NodeId) -> String {
+        format!("`{}`", kind.0)
+    }
+}
+
+enum Rule {
+    Token(&'static str),
+    /// Index of the regex in `Rules::regexes`.
+    #[cfg(feature = "regex")]
+    Regex(usize),
+}
+
+/// Longest-match lexer rules, generated by `#[derive(Lexer)]`.
+///
+/// When several rules match the same length, the one added first wins,
+/// so keywords declared before identifiers stay keywords.
+/// Regex rules are matched together with one `RegexSet` first,
+/// so only the matching ones are run again for their length.
+#[derive(Default)]
+pub struct Rules {
+    rules: Vec<(NodeId, Rule)>,
+    #[cfg(feature = "regex")]
+    regexes: Vec<regex::Regex>,
+    #[cfg(feature = "regex")]
+    set: Option<regex::RegexSet>,
+}
+
+impl Rules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Exact text, like `{`.
+    pub fn token(mut self, kind: NodeId, token: &'static str) -> Self {
+        self.rules.push((kind, Rule::Token(token)));
+        self
+    }
+
+    /// Match of `pattern` anchored at the beginning of the input.
+    ///
+    /// Panics if `pattern` is not a valid regular expression.
+    #[cfg(feature = "regex")]
+    pub fn regex(mut self, kind: NodeId, pattern: &str) -> Self {
+        let regex = regex::Regex::new(&format!("^(?:{})", pattern)).expect("Invalid regex pattern");
+        self.rules.push((kind, Rule::Regex(self.regexes.len())));
+        self.regexes.push(regex);
+        let patterns = self.regexes.iter().map(regex::Regex::as_str);
+        self.set = Some(regex::RegexSet::new(patterns).expect("Invalid regex pattern"));
+        self
+    }
+
+    /// Kind and length of the longest token at the beginning of `input`.
+    pub fn longest(&self, input: &str) -> Option<(NodeId, usize)> {
+        #[cfg(feature = "regex")]
+        let matched = self.set.as_ref().map(|set| set.matches(input));
+        let mut longest: Option<(NodeId, usize)> = None;
+        for (kind, rule) in &self.rules {
+            let len = match rule {
+                Rule::Token(token) if input.starts_with(token) => token.len(),
+                Rule::Token(_) => continue,
+                #[cfg(feature = "regex")]
+                Rule::Regex(index) => match &matched {
+                    Some(matched) if matched.matched(*index) => self.regexes[*index]
+                        .find(input)
+                        .map(|found| found.end())
+                        .unwrap_or_default(),
+                    _ => continue,
+                },
+            };
+            if len > longest.map(|(_, len)| len).unwrap_or_default() {
+                longest = Some((*kind, len));
+            }
+        }
+        longest
+    }
+}
+
+/// Lexer active since `start` offset, see `State::push_mode`.
+#[derive(Clone)]
+pub(crate) struct Mode {
+    lexer: Arc<dyn Lexer>,
+    start: usize,
+}
+
+impl Mode {
+    fn same(&self, other: &Mode) -> bool {
+        Arc::ptr_eq(&self.lexer, &other.lexer) && self.start == other.start
+    }
+}
+
+impl std::fmt::Debug for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mode").field("start", &self.start).finish()
+    }
+}
+
+/// Tokens are lexed lazily and cached, so backtracking doesn't lex the input again.
+#[derive(Clone)]
+pub(crate) struct TokenStream {
+    modes: Vec<Mode>,
+    tokens: Vec<Token>,
+}
+
+impl TokenStream {
+    pub(crate) fn new(lexer: Arc<dyn Lexer>) -> Self {
+        Self {
+            modes: vec![Mode { lexer, start: 0 }],
+            tokens: vec![],
+        }
+    }
+
+    pub(crate) fn lexer(&self) -> &dyn Lexer {
+        self.modes.last().expect("At least one mode").lexer.as_ref()
+    }
+
+    /// Forgets tokens starting at `offset` or later, e.g. when lexer rules change.
+    pub(crate) fn invalidate(&mut self, offset: usize) {
+        let index = self
+            .tokens
+            .iter()
+            .rposition(|token| token.span.range.0 < offset)
+            .map(|index| index + 1)
+            .unwrap_or_default();
+        self.tokens.truncate(index);
+    }
+
+    /// Token at the beginning of `input`. Input nobody recognizes becomes one grapheme `ERROR` token.
+    fn token_at(&mut self, input: &Span) -> Option<Token> {
+        let offset = input.range.0;
+        let index = match self
+            .tokens
+            .binary_search_by_key(&offset, |token| token.span.range.0)
+        {
+            Ok(index) => return Some(self.tokens[index].clone()),
+            Err(index) => index,
+        };
+        if input.is_empty() {
+            return None;
+        }
+        self.tokens.truncate(index);
+        if self
+            .tokens
+            .last()
+            .map(|last| last.span.range.0 + last.span.range.1 > offset)
+            .unwrap_or_default()
+        {
+            self.tokens.pop();
+        }
+
+        let (kind, len) = match self.lexer().lex(input.as_ref()) {
+            Some((kind, len)) if len > 0 => (kind, len),
+            _ => {
+                let len = Cursor::new(input.as_ref()).next().map(|(_, g)| g.len());
+                (NodeId::ERROR, len.unwrap_or_default())
+            }
+        };
+        let mut span = input.clone();
+        span.range.1 = len;
+        let token = Token { kind, span };
+        self.tokens.push(token.clone());
+        Some(token)
+    }
+}
+
+impl State {
+    /// Switches to tokens of `lexer` until `pop_mode`, e.g. inside of a template string.
+    /// Trivia of `lexer` becomes the extra. Starts the token-stream mode if needed.
+    ///
+    /// Tokens lexed in the mode are forgotten when it is popped,
+    /// so backtracking never reuses tokens of another mode.
+    pub fn push_mode(&mut self, lexer: Arc<dyn Lexer>) {
+        let start = self.input.range.0;
+        match self.tokens.as_mut() {
+            Some(stream) => {
+                stream.invalidate(start);
+                stream.modes.push(Mode { lexer, start });
+            }
+            None => self.tokens = Some(TokenStream::new(lexer)),
+        }
+        self.push_extra(trivia().arc());
+    }
+
+    /// Restores lexer and extra active before the last `push_mode`.
+    pub fn pop_mode(&mut self) {
+        self.pop_extra();
+        if let Some(stream) = self.tokens.as_mut() {
+            if let Some(mode) = stream.modes.pop() {
+                stream.invalidate(mode.start);
+            }
+            if stream.modes.is_empty() {
+                self.tokens = None;
+            }
+        }
+    }
+
+    pub(crate) fn modes(&self) -> Option<Vec<Mode>> {
+        self.tokens.as_ref().map(|stream| stream.modes.clone())
+    }
+
+    /// Brings back modes taken with `modes`, e.g. by `rewind`.
+    /// Tokens lexed by modes which are no longer active are forgotten.
+    pub(crate) fn restore_modes(&mut self, modes: Option<Vec<Mode>>) {
+        let modes = match modes {
+            Some(modes) => modes,
+            None => {
+                self.tokens = None;
+                return;
+            }
+        };
+        let stream = self.tokens.get_or_insert_with(|| TokenStream {
+            modes: vec![],
+            tokens: vec![],
+        });
+        let same = stream
+            .modes
+            .iter()
+            .zip(modes.iter())
+            .take_while(|(current, restored)| current.same(restored))
+            .count();
+        let changed = stream.modes[same..]
+            .iter()
+            .chain(modes[same..].iter())
+            .map(|mode| mode.start)
+            .min();
+        if let Some(start) = changed {
+            stream.invalidate(start);
+        }
+        stream.modes = modes;
+    }
+
+    /// Next token including trivia. `None` at the end of input or without a lexer.
+    pub fn current_token(&mut self) -> Option<Token> {
+        let input = self.input.clone();
+        self.tokens.as_mut()?.token_at(&input)
+    }
+
+    /// Next token which is not trivia. Doesn't consume anything.
+    pub fn peek_token(&mut self) -> Option<Token> {
+        let mut input = self.input.clone();
+        loop {
+            let stream = self.tokens.as_mut()?;
+            let token = stream.token_at(&input)?;
+            if !stream.lexer().is_trivia(token.kind) {
+                return Some(token);
+            }
+            input.chomp_chars(token.span.range.1);
+        }
+    }
+
+    /// Kind of the next token which is not trivia.
+    pub fn peek_kind(&mut self) -> Option<NodeId> {
+        self.peek_token().map(|token| token.kind)
+    }
+
+    /// Consumes the next token, even if it is trivia.
+    pub fn chomp_token(&mut self) -> Option<Token> {
+        let token = self.current_token()?;
+        self.input.chomp_chars(token.span.range.1);
+        Some(token)
+    }
+}
+
+/// Token of given `kind` as a node named after it.
+pub fn kind(kind: NodeId) -> impl Parser {
+    move |state: &mut State| match state.current_token() {
+        Some(token) if token.kind == kind => {
+            state.chomp_token();
+            Node {
+                name: kind,
+                span: token.span,
+                children: vec![],
+                alias: vec![],
+            }
+        }
+        token => {
+            let problem = match state.tokens.as_ref() {
+                Some(stream) => TokenError::Expected(stream.lexer().describe(kind)),
+                None => TokenError::NoLexer(kind.0),
+            };
+            let len = token.map(|token| token.span.range.1).unwrap_or_default();
+            raise(problem, len).parse(state)
+        }
+    }
+}
+
+/// Runs `parser` with tokens of `lexer`, see `State::push_mode`.
+pub fn with_mode(lexer: Arc<dyn Lexer>, parser: impl Parser) -> impl Parser {
+    move |state: &mut State| {
+        state.push_mode(lexer.clone());
+        let node = parser.parse(state);
+        state.pop_mode();
+        node
+    }
+}
+
+/// Consecutive trivia tokens. Used as the default extra in token-stream mode.
+pub fn trivia() -> impl Parser {
+    v_node(NodeId::EXTRA, |state| loop {
+        let token = match (state.current_token(), state.tokens.as_ref()) {
+            (Some(token), Some(stream)) if stream.lexer().is_trivia(token.kind) => token,
+            _ => break,
+        };
+        state.chomp_token();
+        state.add_node(Node {
+            name: token.kind,
+            span: token.span,
+            children: vec![],
+            alias: vec![],
+        });
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    node_ids! {
+        Calc:
+            Number,
+            Plus,
+            LParen,
+            RParen,
+            WS,
+            Expr
+    }
+
+    struct CalcLexer;
+
+    impl Lexer for CalcLexer {
+        fn lex(&self, input: &str) -> Option<(NodeId, usize)> {
+            let first = input.chars().next()?;
+            let kind = match first {
+                '+' => Calc::Plus,
+                '(' => Calc::LParen,
+                ')' => Calc::RParen,
+                c if c.is_ascii_digit() => {
+                    let len = input.chars().take_while(|c| c.is_ascii_digit()).count();
+                    return Some((Calc::Number, len));
+                }
+                c if c.is_whitespace() => {
+                    let len = input.len() - input.trim_start().len();
+                    return Some((Calc::WS, len));
+                }
+                _ => return None,
+            };
+            Some((kind, 1))
+        }
+
+        fn is_trivia(&self, kind: NodeId) -> bool {
+            kind == Calc::WS
+        }
+
+        fn describe(&self, kind: NodeId) -> String {
+            match kind {
+                Calc::Number => "a number".into(),
+                Calc::RParen => "`)`".into(),
+                _ => format!("`{}`", kind.0),
+            }
+        }
+    }
+
+    fn expr() -> impl Parser {
+        node(Calc::Expr, |state| {
+            state.add(atom());
+            while state.peek_kind() == Some(Calc::Plus) {
+                state.add(kind(Calc::Plus));
+                state.add(atom());
+            }
+        })
+    }
+
+    fn atom() -> impl Parser {
+        v_node(None, |state| match state.peek_kind() {
+            Some(Calc::LParen) => {
+                state.add(kind(Calc::LParen));
+                state.add(expr());
+                state.add(recover(kind(Calc::RParen)));
+            }
+            _ => state.add(kind(Calc::Number)),
+        })
+    }
+
+    fn leaves(node: &Node, output: &mut Vec<(NodeId, String)>) {
+        if node.children.is_empty() {
+            output.push((node.name, node.span.as_ref().to_string()));
+        }
+        for child in &node.children {
+            leaves(child, output);
+        }
+    }
+
+    #[test]
+    fn lossless_with_trivia() {
+        let input = " 1 + ( 2+3 ) ";
+        let parsed = Parse::new(expr()).lexer(CalcLexer).run(input);
+        let mut actual = vec![];
+        leaves(parsed.root(), &mut actual);
+
+        assert!(parsed.errors.is_empty());
+        assert!(parsed.rest.is_empty());
+        let text = actual.iter().map(|(_, s)| s.as_str()).collect::<String>();
+        assert_eq!(text, input);
+        let kinds = actual
+            .iter()
+            .filter(|(kind, _)| *kind != Calc::WS)
+            .map(|(kind, _)| *kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                Calc::Number,
+                Calc::Plus,
+                Calc::LParen,
+                Calc::Number,
+                Calc::Plus,
+                Calc::Number,
+                Calc::RParen
+            ]
+        );
+    }
+
+    #[test_case("1 +", "I expected a number")]
+    #[test_case("(1 + 2", "I expected `)`")]
+    #[test_case("1 + ?", "I expected a number")]
+    fn problems(input: &str, expected: &str) {
+        let parsed = Parse::new(expr()).lexer(CalcLexer).run(input);
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].problem.to_string(), expected);
+    }
+
+    #[test]
+    fn peek_skips_trivia_without_consuming() {
+        let mut state: State = "  1".into();
+        state.tokens = Some(TokenStream::new(Arc::new(CalcLexer)));
+
+        assert_eq!(state.peek_kind(), Some(Calc::Number));
+        assert_eq!(state.current_token().map(|t| t.kind), Some(Calc::WS));
+        assert_eq!(state.input.as_ref(), "  1");
+    }
+
+    #[test]
+    fn text_parsers_step_over_tokens() {
+        let mut state: State = "12  + 3?".into();
+        state.tokens = Some(TokenStream::new(Arc::new(CalcLexer)));
+
+        assert_eq!(state.peek(1).as_ref(), "12");
+        assert_eq!(state.peek(3).as_ref(), "12  +");
+        assert_eq!(state.chomp(2).as_ref(), "12  ");
+        assert_eq!(state.chomp_while(|t| t != "?").as_ref(), "+ 3");
+        assert_eq!(state.chomp(1).as_ref(), "?");
+    }
+
+    #[test_case("12", "12")]
+    #[test_case("1", "")]
+    #[test_case("12 ", "12 ")]
+    fn str_matches_whole_tokens(token: &'static str, expected: &str) {
+        let mut state: State = "12 + 3".into();
+        state.tokens = Some(TokenStream::new(Arc::new(CalcLexer)));
+        let actual = Parser::<Span>::parse(&token, &mut state);
+
+        assert_eq!(actual.as_ref(), expected);
+    }
+
+    #[test]
+    fn kind_without_lexer_is_a_problem() {
+        let parsed = State::parse("1", kind(Calc::Number));
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(
+            parsed.errors[0].problem.to_string(),
+            "I expected `Number` token, but there is no lexer, see `Parse::lexer`"
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    mod derived {
+        use super::*;
+        use test_case::test_case;
+
+        #[derive(alder_derive::Lexer)]
+        #[lexer(ids = "Tok")]
+        enum Token {
+            #[token("{")]
+            LBrace,
+            #[token("}")]
+            RBrace,
+            #[token("true")]
+            True,
+            #[regex("[a-z]+", name = "an identifier")]
+            Ident,
+            #[regex("[0-9]+")]
+            Number,
+            #[trivia]
+            #[regex(r"\s+")]
+            Whitespace,
+            Object,
+        }
+
+        #[test_case("{}", Tok::LBrace, 1)]
+        #[test_case("true}", Tok::True, 4)]
+        #[test_case("truer", Tok::Ident, 5)]
+        #[test_case("42 ", Tok::Number, 2)]
+        #[test_case(" \n x", Tok::Whitespace, 3)]
+        fn longest_match(input: &str, kind: NodeId, len: usize) {
+            assert_eq!(Tok.lex(input), Some((kind, len)));
+        }
+
+        #[test]
+        fn kinds() {
+            assert_eq!(Tok.lex("?"), None);
+            assert!(Tok.is_trivia(Tok::Whitespace));
+            assert!(!Tok.is_trivia(Tok::Ident));
+            assert_eq!(NodeId::from(Token::Object), NodeId("Object"));
+        }
+
+        #[test_case(Token::LBrace, "`{`")]
+        #[test_case(Token::Ident, "an identifier")]
+        #[test_case(Token::Number, "`Number`")]
+        fn names(token: Token, expected: &str) {
+            assert_eq!(token.to_string(), expected);
+            assert_eq!(Tok.describe(token.into()), expected);
+        }
+
+        #[test]
+        fn parses() {
+            let object = node(Tok::Object, |state| {
+                state.add(kind(Tok::LBrace));
+                state.add(kind(Tok::Ident));
+                state.add(kind(Tok::RBrace));
+            });
+            let parsed = Parse::new(object).lexer(Tok).run("{ key 1}");
+
+            assert_eq!(parsed.errors.len(), 1);
+            assert_eq!(parsed.errors[0].problem.to_string(), "I expected `}`");
+        }
+
+        /// Every character is a token.
+        struct Chars;
+
+        impl Lexer for Chars {
+            fn lex(&self, input: &str) -> Option<(NodeId, usize)> {
+                let c = input.chars().next()?;
+                Some((NodeId::TOKEN, c.len_utf8()))
+            }
+        }
+
+        #[test]
+        fn modes_switch_tokens_and_trivia() {
+            let mut state: State = "ab c".into();
+            state.tokens = Some(TokenStream::new(Arc::new(Tok)));
+            assert_eq!(state.peek_token().map(|t| t.span.range), Some((0, 2)));
+
+            state.push_mode(Arc::new(Chars));
+            assert_eq!(state.peek_token().map(|t| t.span.range), Some((0, 1)));
+            state.chomp_token();
+            state.chomp_token();
+            assert_eq!(state.peek_kind(), Some(NodeId::TOKEN));
+
+            state.pop_mode();
+            assert_eq!(state.peek_token().map(|t| t.span.range), Some((3, 1)));
+        }
+
+        #[test]
+        fn popped_mode_tokens_are_forgotten() {
+            let mut state: State = "ab".into();
+            state.tokens = Some(TokenStream::new(Arc::new(Tok)));
+            let checkpoint = state.checkpoint();
+            state.push_mode(Arc::new(Chars));
+            state.chomp_token();
+            state.pop_mode();
+            state.rewind(checkpoint);
+
+            assert_eq!(state.peek_token().map(|t| t.span.range), Some((0, 2)));
+        }
+
+        #[test]
+        fn rewind_restores_mode() {
+            let mut state: State = "ab".into();
+            state.tokens = Some(TokenStream::new(Arc::new(Tok)));
+            let checkpoint = state.checkpoint();
+            state.push_mode(Arc::new(Chars));
+            state.chomp_token();
+            state.rewind(checkpoint);
+
+            assert_eq!(state.peek_token().map(|t| t.span.range), Some((0, 2)));
+        }
+
+        #[test]
+        fn with_mode_starts_token_stream() {
+            let parser = node(Tok::Object, |state| {
+                state.add(kind(Tok::Ident));
+                state.add(kind(Tok::Number));
+            });
+            let parsed = State::parse(" a 1 ", with_mode(Arc::new(Tok), parser));
+
+            assert!(parsed.errors.is_empty());
+            assert!(parsed.rest.is_empty());
+            assert!(parsed.nodes[0].children[0].is(Tok::Whitespace));
+        }
+    }
+}
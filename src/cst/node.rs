@@ -9,6 +9,7 @@ impl NodeId {
     pub const ERROR: Self = NodeId("ERROR");
     pub const EXTRA: Self = NodeId("EXTRA");
     pub const VIRTUAL: Self = NodeId("VIRTUAL");
+    pub const KEYWORD: Self = NodeId("KEYWORD");
 
     pub const NO_CONTEXT: &'static [Self] = &[Self::ROOT, Self::VIRTUAL];
 }
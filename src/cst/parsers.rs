@@ -1,19 +1,41 @@
 use crate::*;
 
+mod class;
+mod combinator;
 mod common;
 mod extra;
+mod grammar;
+mod indent;
+mod keyword;
 mod lexer;
+mod list;
+mod literal;
 mod node;
+mod number;
+#[cfg(feature = "regex")]
+mod pattern;
 mod pratt;
 mod problem;
+mod string;
 
+pub use class::*;
+pub use combinator::*;
 pub use common::*;
 pub use extra::*;
+pub use grammar::*;
+pub use indent::*;
+pub use keyword::*;
 pub use lexer::utf::*;
 pub use lexer::*;
+pub use list::*;
+pub use literal::*;
 pub use node::*;
+pub use number::*;
+#[cfg(feature = "regex")]
+pub use pattern::*;
 pub use pratt::*;
 pub use problem::*;
+pub use string::*;
 
 use std::marker::PhantomData;
 pub struct Map<P, F, T>
@@ -74,3 +96,106 @@ pub trait NodeParserExt: Parser<Node> {
 }
 
 impl<P> NodeParserExt for P where P: Parser<Node> {}
+
+/// Combinators for both node parsers and recognizers.
+/// `T` is inferred from where the combinator is used, e.g. `"-".or("+")` passed to `recognize`.
+/// A node parser fails when it fails before recognizing anything, a recognizer when its span is empty.
+/// Failed combinators consume nothing.
+pub trait ParserExt<T>: Parser<T> {
+    /// Parses `self` followed by `next`.
+    fn then<P: Parser<T>>(self, next: P) -> Then<Self, P, T>
+    where
+        Self: Sized,
+    {
+        Then(self, next, PhantomData)
+    }
+
+    /// Tries `other` when `self` fails.
+    fn or<P: Parser<T>>(self, other: P) -> Or<Self, P, T>
+    where
+        Self: Sized,
+    {
+        Or(self, other, PhantomData)
+    }
+
+    /// Produces nothing when `self` fails, without reporting its problem.
+    fn opt(self) -> Opt<Self, T>
+    where
+        Self: Sized,
+    {
+        Opt(self, PhantomData)
+    }
+
+    fn many0(self) -> Many<Self, T>
+    where
+        Self: Sized,
+    {
+        Many {
+            parser: self,
+            min: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn many1(self) -> Many<Self, T>
+    where
+        Self: Sized,
+    {
+        Many {
+            parser: self,
+            min: 1,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Zero or more `self` separated by `separator`. Trailing separator is not consumed.
+    fn sep_by<S: Parser<T>>(self, separator: S) -> SepBy<Self, S, T>
+    where
+        Self: Sized,
+    {
+        SepBy {
+            parser: self,
+            separator,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// `self` between `open` and `close`. Node parsers recover on `close`.
+    fn delimited<O: Parser<T>, C: Parser<T>>(self, open: O, close: C) -> Delimited<O, Self, C, T>
+    where
+        Self: Sized,
+    {
+        Delimited {
+            open,
+            parser: self,
+            close,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Replaces the problem reported when `self` fails, e.g. by `chomp_while1`.
+    fn label<Pr: Problem + Clone + 'static>(self, problem: Pr) -> Label<Self, Pr, T>
+    where
+        Self: Sized,
+    {
+        Label {
+            parser: self,
+            problem,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Problems raised inside `self` are reported as found while parsing `name`.
+    fn context(self, name: NodeId) -> Context<Self, T>
+    where
+        Self: Sized,
+    {
+        Context {
+            parser: self,
+            name,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, P> ParserExt<T> for P where P: Parser<T> {}
@@ -0,0 +1,241 @@
+use crate::Problem;
+use std::fmt::{Display, Formatter};
+use std::ops::{BitOr, Not, RangeInclusive};
+
+#[derive(Debug, Clone)]
+enum Class {
+    Char(char),
+    OneOf(&'static str),
+    Range(char, char),
+    Named(&'static str, fn(char) -> bool),
+    Union(Vec<Class>),
+    Not(Box<Class>),
+}
+
+impl Class {
+    fn contains(&self, c: char) -> bool {
+        match self {
+            Class::Char(expected) => *expected == c,
+            Class::OneOf(chars) => chars.contains(c),
+            Class::Range(from, to) => (*from..=*to).contains(&c),
+            Class::Named(_, f) => f(c),
+            Class::Union(classes) => classes.iter().any(|class| class.contains(c)),
+            Class::Not(class) => !class.contains(c),
+        }
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Class::Char(c) => write!(f, "`{}`", c),
+            Class::OneOf(chars) if chars.chars().count() == 1 => write!(f, "`{}`", chars),
+            Class::OneOf(chars) => {
+                let chars = chars
+                    .chars()
+                    .map(|c| format!("`{}`", c))
+                    .collect::<Vec<_>>();
+                write!(f, "one of {}", chars.join(", "))
+            }
+            Class::Range(from, to) => write!(f, "`{}` to `{}`", from, to),
+            Class::Named(name, _) => f.write_str(name),
+            Class::Union(classes) => {
+                let mut classes = classes
+                    .iter()
+                    .map(|class| class.to_string())
+                    .collect::<Vec<_>>();
+                let last = classes.pop().unwrap_or_default();
+                if classes.is_empty() {
+                    return f.write_str(&last);
+                }
+                write!(f, "{} or {}", classes.join(", "), last)
+            }
+            Class::Not(class) => write!(f, "anything but {}", class),
+        }
+    }
+}
+
+/// Set of characters like `one_of("+-")`, `range('a'..='z')` or `class!(alpha | digit | '_')`,
+/// combined with `|` and negated with `!`.
+///
+/// A step matches when its first `char` is in the set,
+/// so in grapheme mode `e\u{301}` is a part of `alpha()`.
+/// Pass it to `chomp_while`, `chomp_if` or `chomp_until` with `into_fn`.
+#[derive(Debug, Clone)]
+pub struct CharClass(Class);
+
+impl CharClass {
+    pub fn contains(&self, c: char) -> bool {
+        self.0.contains(c)
+    }
+
+    /// Whether the first `char` of `s` is in the set.
+    pub fn test(&self, s: &str) -> bool {
+        s.chars()
+            .next()
+            .map(|c| self.contains(c))
+            .unwrap_or_default()
+    }
+
+    /// The set as a predicate, e.g. `chomp_while(class!(alpha | '_').into_fn())`.
+    pub fn into_fn(self) -> impl Fn(&str) -> bool {
+        move |s| self.test(s)
+    }
+
+    /// "I expected ..." problem describing the set,
+    /// e.g. `chomp_while1(digit().into_fn()).label(digit().expected())`.
+    pub fn expected(&self) -> impl Problem + Clone {
+        Expected(self.clone())
+    }
+
+    /// Union of all `classes`, used by `class!`.
+    pub fn union(classes: Vec<CharClass>) -> Self {
+        let classes = classes
+            .into_iter()
+            .flat_map(|class| match class.0 {
+                Class::Union(classes) => classes,
+                class => vec![class],
+            })
+            .collect();
+        CharClass(Class::Union(classes))
+    }
+}
+
+impl Display for CharClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Expected(CharClass);
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "I expected {}", self.0)
+    }
+}
+
+impl BitOr for CharClass {
+    type Output = CharClass;
+
+    fn bitor(self, other: CharClass) -> CharClass {
+        CharClass::union(vec![self, other])
+    }
+}
+
+impl Not for CharClass {
+    type Output = CharClass;
+
+    fn not(self) -> CharClass {
+        match self.0 {
+            Class::Not(class) => CharClass(*class),
+            class => CharClass(Class::Not(Box::new(class))),
+        }
+    }
+}
+
+impl From<char> for CharClass {
+    fn from(c: char) -> Self {
+        CharClass(Class::Char(c))
+    }
+}
+
+impl From<&'static str> for CharClass {
+    fn from(chars: &'static str) -> Self {
+        one_of(chars)
+    }
+}
+
+pub fn one_of(chars: &'static str) -> CharClass {
+    CharClass(Class::OneOf(chars))
+}
+
+pub fn none_of(chars: &'static str) -> CharClass {
+    !one_of(chars)
+}
+
+pub fn range(range: RangeInclusive<char>) -> CharClass {
+    CharClass(Class::Range(*range.start(), *range.end()))
+}
+
+pub fn alpha() -> CharClass {
+    CharClass(Class::Named("a letter", char::is_alphabetic))
+}
+
+pub fn digit() -> CharClass {
+    CharClass(Class::Named("a digit", |c| c.is_ascii_digit()))
+}
+
+pub fn hex_digit() -> CharClass {
+    CharClass(Class::Named("a hex digit", |c| c.is_ascii_hexdigit()))
+}
+
+pub fn alphanumeric() -> CharClass {
+    CharClass(Class::Named("a letter or digit", char::is_alphanumeric))
+}
+
+pub fn whitespace() -> CharClass {
+    CharClass(Class::Named("a whitespace", char::is_whitespace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use test_case::test_case;
+
+    #[test_case(one_of("+-"), "-", true)]
+    #[test_case(one_of("+-"), "*", false)]
+    #[test_case(none_of("\"\\"), "a", true)]
+    #[test_case(none_of("\"\\"), "\"", false)]
+    #[test_case(none_of("\"\\"), "", false)]
+    #[test_case(range('a'..='f'), "c", true)]
+    #[test_case(range('a'..='f'), "g", false)]
+    #[test_case(class!(alpha | digit | '_'), "_", true)]
+    #[test_case(class!(alpha | digit | '_'), "\u{105}", true)]
+    #[test_case(class!(alpha | digit | '_'), "-", false)]
+    #[test_case(!class!(digit | "+-"), "x", true)]
+    #[test_case(alpha(), "e\u{301}", true)]
+    fn test(class: CharClass, input: &str, expected: bool) {
+        assert_eq!(class.test(input), expected);
+    }
+
+    #[test_case(one_of("_"), "`_`")]
+    #[test_case(one_of("+-"), "one of `+`, `-`")]
+    #[test_case(none_of("\""), "anything but `\"`")]
+    #[test_case(range('a'..='z') | digit(), "`a` to `z` or a digit")]
+    #[test_case(class!(alpha | digit | '_'), "a letter, a digit or `_`")]
+    #[test_case(!!hex_digit(), "a hex digit")]
+    fn display(class: CharClass, expected: &str) {
+        assert_eq!(class.to_string(), expected);
+    }
+
+    #[test_case(chomp_while(class!(alpha | '_').into_fn()), "snake_case1", "snake_case")]
+    #[test_case(chomp_until(one_of(",]").into_fn()), "abc, d", "abc")]
+    #[test_case(chomp_if((!whitespace()).into_fn()), "a b", "a")]
+    fn with_chomp(parser: impl Parser<Span>, input: &str, expected: &str) {
+        let mut state: State = input.into();
+        let actual = parser.parse(&mut state);
+
+        assert_eq!(actual.as_ref(), expected);
+    }
+
+    #[test]
+    fn expected_message() {
+        let digits = chomp_while1(digit().into_fn()).label(digit().expected());
+        let parsed = State::parse("x", recognize(NodeId("Digits"), digits));
+
+        assert_eq!(parsed.errors[0].problem.to_string(), "I expected a digit");
+    }
+
+    mod without_imports {
+        #[test]
+        fn class_macro_is_qualified() {
+            let class = class!(alpha | digit | '_');
+
+            assert!(class.test("_"));
+            assert!(!class.test("-"));
+        }
+    }
+}
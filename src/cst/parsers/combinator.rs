@@ -0,0 +1,586 @@
+use crate::*;
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+/// Returns true if the node failed before recognizing anything,
+/// which means an alternative can be tried instead.
+pub(crate) fn failed_early(node: &Node) -> bool {
+    node.is(NodeId::ERROR)
+        || node
+            .children
+            .iter()
+            .find(|child| !child.is(NodeId::EXTRA))
+            .map(failed_early)
+            .unwrap_or_default()
+}
+
+/// Adds node produced by parser and returns true if it failed early.
+pub(crate) fn try_add(state: &mut State, parser: &impl Parser) -> bool {
+    let failed = Cell::new(false);
+    state.add(|state: &mut State| {
+        let node = parser.parse(state);
+        failed.set(failed_early(&node));
+        node
+    });
+    failed.get()
+}
+
+fn join(first: Span, second: Span) -> Span {
+    let mut span = first;
+    span.range.1 = second.range.0 + second.range.1 - span.range.0;
+    span
+}
+
+fn empty(state: &State) -> Span {
+    let mut span = state.input.clone();
+    span.range.1 = 0;
+    span
+}
+
+/// Rewinds a failed text parser to `checkpoint`, but keeps the failure for `recognize`.
+fn rewind_failed(state: &mut State, checkpoint: Checkpoint) -> Span {
+    let failure = state.failure.take();
+    state.rewind(checkpoint);
+    state.failure = failure;
+    empty(state)
+}
+
+/// Left recursive `name = name extension | base`, e.g. `expr = expr "." ident | atom`.
+/// The seed parsed by `base` is wrapped in `name` nodes as long as `extension` recognizes something,
+/// so `a.b.c` becomes `((a.b).c)` in linear time and without descending.
+pub fn left_rec(name: NodeId, base: impl Parser, extension: impl Fn(&mut State)) -> impl Parser {
+    move |state: &mut State| {
+        let mut seed = base.parse(state);
+        if failed_early(&seed) {
+            return seed;
+        }
+        loop {
+            let checkpoint = state.checkpoint();
+            let start = state.input.clone();
+            let grown = node(name, &extension).parse(state);
+            if failed_early(&grown) || state.input == start {
+                state.rewind(checkpoint);
+                return seed;
+            }
+
+            let parts = Cell::new(Some((seed, grown.children)));
+            seed = node(name, |state| {
+                let (left, rest) = parts.take().expect("Seed");
+                state.add_node(left);
+                for child in rest {
+                    state.add_node(child);
+                }
+            })
+            .parse(state);
+        }
+    }
+}
+
+pub struct Then<A, B, T>(pub(crate) A, pub(crate) B, pub(crate) PhantomData<T>);
+
+impl<A: Parser, B: Parser> Parser for Then<A, B, Node> {
+    fn parse(&self, state: &mut State) -> Node {
+        v_node(None, |state| {
+            state.add(|state: &mut State| self.0.parse(state));
+            state.add(|state: &mut State| self.1.parse(state));
+        })
+        .parse(state)
+    }
+}
+
+impl<A: Parser<Span>, B: Parser<Span>> Parser<Span> for Then<A, B, Span> {
+    fn parse(&self, state: &mut State) -> Span {
+        let checkpoint = state.checkpoint();
+        let first = self.0.parse(state);
+        if first.is_empty() {
+            return first;
+        }
+        let second = self.1.parse(state);
+        if second.is_empty() {
+            return rewind_failed(state, checkpoint);
+        }
+        join(first, second)
+    }
+}
+
+pub struct Or<A, B, T>(pub(crate) A, pub(crate) B, pub(crate) PhantomData<T>);
+
+impl<A: Parser, B: Parser> Parser for Or<A, B, Node> {
+    fn parse(&self, state: &mut State) -> Node {
+        let checkpoint = state.checkpoint();
+        let node = self.0.parse(state);
+        if !failed_early(&node) {
+            return node;
+        }
+        state.rewind(checkpoint);
+        self.1.parse(state)
+    }
+}
+
+impl<A: Parser<Span>, B: Parser<Span>> Parser<Span> for Or<A, B, Span> {
+    fn parse(&self, state: &mut State) -> Span {
+        let checkpoint = state.checkpoint();
+        let span = self.0.parse(state);
+        if !span.is_empty() {
+            return span;
+        }
+        state.rewind(checkpoint);
+        self.1.parse(state)
+    }
+}
+
+pub struct Opt<P, T>(pub(crate) P, pub(crate) PhantomData<T>);
+
+impl<P: Parser> Parser for Opt<P, Node> {
+    fn parse(&self, state: &mut State) -> Node {
+        let checkpoint = state.checkpoint();
+        let node = self.0.parse(state);
+        if !failed_early(&node) {
+            return node;
+        }
+        state.rewind(checkpoint);
+        none().parse(state)
+    }
+}
+
+impl<P: Parser<Span>> Parser<Span> for Opt<P, Span> {
+    fn parse(&self, state: &mut State) -> Span {
+        let checkpoint = state.checkpoint();
+        let span = self.0.parse(state);
+        if !span.is_empty() {
+            return span;
+        }
+        state.rewind(checkpoint);
+        empty(state)
+    }
+}
+
+pub struct Many<P, T> {
+    pub(crate) parser: P,
+    pub(crate) min: usize,
+    pub(crate) _phantom: PhantomData<T>,
+}
+
+impl<P: Parser> Parser for Many<P, Node> {
+    fn parse(&self, state: &mut State) -> Node {
+        v_node(None, |state| {
+            let mut count = 0;
+            loop {
+                let checkpoint = state.checkpoint();
+                let start = state.input.clone();
+                let failed = try_add(state, &self.parser);
+                if failed && count >= self.min {
+                    state.rewind(checkpoint);
+                    break;
+                }
+                count += 1;
+                if failed || state.input == start {
+                    break;
+                }
+            }
+        })
+        .parse(state)
+    }
+}
+
+impl<P: Parser<Span>> Parser<Span> for Many<P, Span> {
+    fn parse(&self, state: &mut State) -> Span {
+        let start = state.checkpoint();
+        let mut output = empty(state);
+        let mut count = 0;
+        loop {
+            let checkpoint = state.checkpoint();
+            let span = self.parser.parse(state);
+            if span.is_empty() {
+                if count < self.min {
+                    return rewind_failed(state, start);
+                }
+                state.rewind(checkpoint);
+                return output;
+            }
+            output = join(output, span);
+            count += 1;
+        }
+    }
+}
+
+pub struct SepBy<P, S, T> {
+    pub(crate) parser: P,
+    pub(crate) separator: S,
+    pub(crate) _phantom: PhantomData<T>,
+}
+
+impl<P: Parser, S: Parser> Parser for SepBy<P, S, Node> {
+    fn parse(&self, state: &mut State) -> Node {
+        v_node(None, |state| {
+            let checkpoint = state.checkpoint();
+            if try_add(state, &self.parser) {
+                state.rewind(checkpoint);
+                return;
+            }
+            loop {
+                let checkpoint = state.checkpoint();
+                if try_add(state, &self.separator) || try_add(state, &self.parser) {
+                    state.rewind(checkpoint);
+                    break;
+                }
+            }
+        })
+        .parse(state)
+    }
+}
+
+impl<P: Parser<Span>, S: Parser<Span>> Parser<Span> for SepBy<P, S, Span> {
+    fn parse(&self, state: &mut State) -> Span {
+        let checkpoint = state.checkpoint();
+        let mut output = self.parser.parse(state);
+        if output.is_empty() {
+            return rewind_failed(state, checkpoint);
+        }
+        loop {
+            let checkpoint = state.checkpoint();
+            let separator = self.separator.parse(state);
+            let item = if separator.is_empty() {
+                separator
+            } else {
+                self.parser.parse(state)
+            };
+            if item.is_empty() {
+                state.rewind(checkpoint);
+                return output;
+            }
+            output = join(output, item);
+        }
+    }
+}
+
+pub struct Delimited<O, P, C, T> {
+    pub(crate) open: O,
+    pub(crate) parser: P,
+    pub(crate) close: C,
+    pub(crate) _phantom: PhantomData<T>,
+}
+
+impl<O: Parser, P: Parser, C: Parser> Parser for Delimited<O, P, C, Node> {
+    fn parse(&self, state: &mut State) -> Node {
+        v_node(None, |state| {
+            state.add(|state: &mut State| self.open.parse(state));
+            state.add(|state: &mut State| self.parser.parse(state));
+            state.add(recover(|state: &mut State| self.close.parse(state)));
+        })
+        .parse(state)
+    }
+}
+
+impl<O: Parser<Span>, P: Parser<Span>, C: Parser<Span>> Parser<Span>
+    for Delimited<O, P, C, Span>
+{
+    fn parse(&self, state: &mut State) -> Span {
+        let checkpoint = state.checkpoint();
+        let open = self.open.parse(state);
+        let parsed = if open.is_empty() {
+            open.clone()
+        } else {
+            self.parser.parse(state)
+        };
+        let close = if parsed.is_empty() {
+            parsed
+        } else {
+            self.close.parse(state)
+        };
+        if close.is_empty() {
+            return rewind_failed(state, checkpoint);
+        }
+        join(open, close)
+    }
+}
+
+pub struct Label<P, Pr, T> {
+    pub(crate) parser: P,
+    pub(crate) problem: Pr,
+    pub(crate) _phantom: PhantomData<T>,
+}
+
+impl<P: Parser, Pr: Problem + Clone + 'static> Parser for Label<P, Pr, Node> {
+    fn parse(&self, state: &mut State) -> Node {
+        let errors = state.errors.len();
+        let node = self.parser.parse(state);
+        if failed_early(&node) {
+            if let Some(error) = state.errors.get_mut(errors) {
+                error.problem = Box::new(self.problem.clone());
+            }
+        }
+        node
+    }
+}
+
+impl<P: Parser<Span>, Pr: Problem + Clone + 'static> Parser<Span> for Label<P, Pr, Span> {
+    fn parse(&self, state: &mut State) -> Span {
+        let span = self.parser.parse(state);
+        if span.is_empty() {
+            if let Some(failure) = state.failure.as_mut() {
+                failure.problem = Rc::new(self.problem.clone());
+            }
+        }
+        span
+    }
+}
+
+pub struct Context<P, T> {
+    pub(crate) parser: P,
+    pub(crate) name: NodeId,
+    pub(crate) _phantom: PhantomData<T>,
+}
+
+impl<P: Parser> Parser for Context<P, Node> {
+    fn parse(&self, state: &mut State) -> Node {
+        v_node(self.name, |state| {
+            state.add(|state: &mut State| self.parser.parse(state));
+        })
+        .parse(state)
+        .with_no_alias()
+    }
+}
+
+impl<P: Parser<Span>> Parser<Span> for Context<P, Span> {
+    fn parse(&self, state: &mut State) -> Span {
+        let span = self.parser.parse(state);
+        if span.is_empty() {
+            if let Some(failure) = state.failure.as_mut() {
+                failure.context.push(self.name);
+            }
+        }
+        span
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn extra() -> std::sync::Arc<dyn Parser> {
+        recognize(NodeId("WS"), chomp_while(is_ws)).arc()
+    }
+
+    fn boolean() -> impl Parser {
+        token("true").or(token("false"))
+    }
+
+    fn fluent() -> impl Parser {
+        with_extra(
+            extra(),
+            node(NodeId("List"), |state| {
+                state.add(
+                    boolean()
+                        .sep_by(token(","))
+                        .delimited(token("["), token("]")),
+                );
+            }),
+        )
+    }
+
+    fn imperative() -> impl Parser {
+        with_extra(
+            extra(),
+            node(NodeId("List"), |state| {
+                state.add("[");
+                if let "t" | "f" = state.peek(1).as_ref() {
+                    loop {
+                        match state.peek(1).as_ref() {
+                            "t" => state.add("true"),
+                            _ => state.add("false"),
+                        }
+                        if state.peek(1).as_ref() != "," {
+                            break;
+                        }
+                        state.add(",");
+                    }
+                }
+                state.add(recover("]"));
+            }),
+        )
+    }
+
+    #[test_case("[]")]
+    #[test_case("[ true ]")]
+    #[test_case("[true, false , true]")]
+    #[test_case("[true, false")]
+    fn same_as_imperative(input: &str) {
+        let expected = State::parse(input, imperative());
+        let actual = State::parse(input, fluent());
+
+        assert_eq!(actual.nodes, expected.nodes);
+        assert_eq!(actual.rest, expected.rest);
+        assert_eq!(actual.errors.len(), expected.errors.len());
+    }
+
+    #[test]
+    fn or_reports_last_alternative() {
+        let parsed = State::parse("maybe", boolean());
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].problem.to_string(), "I expected `false`");
+    }
+
+    #[test]
+    fn opt() {
+        let parser = || token("-").opt().then(token("1"));
+
+        let parsed = State::parse("-1", parser());
+        assert_eq!(parsed.nodes.len(), 2);
+
+        let parsed = State::parse("1", parser());
+        assert_eq!(parsed.nodes.len(), 1);
+        assert!(parsed.errors.is_empty());
+    }
+
+    #[test]
+    fn many() {
+        let parsed = State::parse("aaab", token("a").many0());
+        assert_eq!(parsed.nodes.len(), 3);
+        assert_eq!(parsed.rest.as_ref(), "b");
+
+        let parsed = State::parse("b", token("a").many0());
+        assert!(parsed.nodes.is_empty());
+        assert!(parsed.errors.is_empty());
+
+        let parsed = State::parse("b", token("a").many1());
+        assert_eq!(parsed.errors.len(), 1);
+    }
+
+    #[test]
+    fn label_and_context() {
+        let parser = boolean()
+            .label("I expected a boolean")
+            .context(NodeId("Flag"));
+        let parsed = State::parse("maybe", parser);
+
+        let error = &parsed.errors[0];
+        assert_eq!(error.problem.to_string(), "I expected a boolean");
+        assert_eq!(error.context.last().map(|c| c.node), Some(NodeId("Flag")));
+        assert!(parsed.nodes[0].alias.is_empty());
+    }
+
+    #[test]
+    fn sep_by_leaves_trailing_separator() {
+        let parsed = State::parse("a,a,", token("a").sep_by(token(",")));
+        assert_eq!(parsed.nodes.len(), 3);
+        assert_eq!(parsed.rest.as_ref(), ",");
+        assert!(parsed.errors.is_empty());
+
+        let parsed = State::parse("b", token("a").sep_by(token(",")));
+        assert!(parsed.nodes.is_empty());
+        assert!(parsed.errors.is_empty());
+    }
+
+    fn member() -> impl Parser {
+        with_extra(
+            extra(),
+            left_rec(NodeId("Member"), ident(NodeId("Ident")), |state| {
+                state.add(".");
+                state.add(ident(NodeId("Ident")));
+            }),
+        )
+    }
+
+    fn sexp(node: &Node) -> String {
+        let children = node
+            .children
+            .iter()
+            .filter(|child| !child.is(NodeId::EXTRA))
+            .map(sexp)
+            .collect::<Vec<_>>();
+        if children.is_empty() {
+            node.span.as_ref().to_string()
+        } else {
+            format!("({})", children.join(" "))
+        }
+    }
+
+    #[test_case("a", "a", 0)]
+    #[test_case("a.b", "(a . b)", 0)]
+    #[test_case("a . b.c", "((a . b) . c)", 0)]
+    #[test_case("a.b.", "((a . b) . )", 1)]
+    #[test_case("a.b x", "(a . b)", 0)]
+    fn left_recursion(input: &str, expected: &str, errors: usize) {
+        let parsed = State::parse(input, member());
+
+        assert_eq!(sexp(&parsed.nodes[0]), expected);
+        assert_eq!(parsed.errors.len(), errors);
+    }
+
+    #[test]
+    fn left_recursion_does_not_descend() {
+        let input = format!("a{}", ".b".repeat(10_000));
+        let parsed = Parse::new(member()).max_depth(4).run(&input);
+
+        assert!(parsed.errors.is_empty());
+        assert!(parsed.rest.is_empty());
+        assert_eq!(parsed.nodes[0].children.len(), 3);
+    }
+
+    #[test_case(chomp_while(is_digits).then("."), "12.", "12.", "")]
+    #[test_case(chomp_while(is_digits).then("."), "12", "", "12")]
+    #[test_case("0x".or("0b"), "0b1", "0b", "1")]
+    #[test_case("ab".many1(), "ababa", "abab", "a")]
+    #[test_case(chomp_while(is_digits).sep_by("_"), "1_000_", "1_000", "_")]
+    #[test_case(chomp_while1(is_digits).sep_by("_"), "x", "", "x")]
+    #[test_case(chomp_while1(is_digits).opt(), "x", "", "x")]
+    #[test_case(chomp_while(is_digits).delimited("(", ")"), "(12)", "(12)", "")]
+    #[test_case(chomp_while(is_digits).delimited("(", ")"), "(12", "", "(12")]
+    fn span_combinators(
+        parser: impl Parser<Span>,
+        input: &str,
+        expected: &str,
+        expected_rest: &str,
+    ) {
+        let mut state: State = input.into();
+        let actual = parser.parse(&mut state);
+        assert_eq!(actual.as_ref(), expected);
+        assert_eq!(state.input.as_ref(), expected_rest);
+        assert!(state.errors.is_empty());
+    }
+
+    #[test_case(chomp_if1(is_digits).or("a"), "a", "")]
+    #[test_case(chomp_if1(is_digits).many1(), "12a", "a")]
+    #[test_case(chomp_if1(is_digits).then("a").many0(), "1a1b", "1b")]
+    fn span_combinators_rewind_failed_attempts(
+        parser: impl Parser<Span>,
+        input: &str,
+        expected_rest: &str,
+    ) {
+        let mut state: State = input.into();
+        parser.parse(&mut state);
+
+        assert_eq!(state.input.as_ref(), expected_rest);
+        assert!(state.failure.is_none());
+    }
+
+    #[test]
+    fn span_many_keeps_failure() {
+        let mut state: State = "1a".into();
+        let actual = chomp_if1(is_digits)
+            .then(chomp_if1(is_digits))
+            .many1()
+            .parse(&mut state);
+
+        assert!(actual.is_empty());
+        assert_eq!(state.input.as_ref(), "1a");
+        assert!(state.failure.is_some());
+    }
+
+    #[test]
+    fn span_label_and_context() {
+        let mut state: State = "x".into();
+        let int = chomp_while1(is_digits)
+            .label("I expected a number")
+            .context(NodeId("Int"));
+        state.add(recognize(NodeId("Number"), int));
+
+        let error = &state.errors[0];
+        assert_eq!(error.problem.to_string(), "I expected a number");
+        assert_eq!(error.context.last().map(|c| c.node), Some(NodeId("Int")));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::*;
+use derive_more::Display;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+pub(crate) type Definitions = BTreeMap<&'static str, Arc<dyn Parser>>;
+
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+pub enum GrammarError {
+    #[display(fmt = "I expected rule `{}` to be defined", _0)]
+    Undefined(&'static str),
+
+    #[display(fmt = "I found rule `{}` that is never used", _0)]
+    Unused(&'static str),
+
+    #[display(fmt = "I found rule `{}` calling itself without consuming input", _0)]
+    Cycle(&'static str),
+}
+
+/// Registry of named rules referencing each other with `rule`.
+/// Every rule is built once and shared between all references.
+#[derive(Clone)]
+pub struct Grammar {
+    start: &'static str,
+    rules: Arc<Definitions>,
+    references: BTreeMap<&'static str, Vec<&'static str>>,
+}
+
+impl Grammar {
+    /// Parsing the grammar parses the `start` rule.
+    pub fn new(start: &'static str) -> Self {
+        Self {
+            start,
+            rules: Default::default(),
+            references: Default::default(),
+        }
+    }
+
+    /// Registers `name` built by `build`.
+    /// References created with `References::rule` while building are remembered for `check`,
+    /// so create them outside of parsing closures, `Rule` is `Copy`.
+    pub fn rule<P: Parser + 'static>(
+        mut self,
+        name: &'static str,
+        build: impl FnOnce(&mut References) -> P,
+    ) -> Self {
+        let mut references = References::default();
+        let parser = build(&mut references).arc();
+
+        Arc::make_mut(&mut self.rules).insert(name, parser);
+        self.references.insert(name, references.names);
+        self
+    }
+
+    /// Finds references to undefined rules, rules unreachable from the start rule
+    /// and rules calling themselves without consuming input.
+    ///
+    /// Cycles are found by parsing every rule from empty input,
+    /// so a cycle behind a branch taken only on non-empty input, e.g. after `peek`, is missed.
+    pub fn check(&self) -> Vec<GrammarError> {
+        let mut errors = vec![];
+        let mut reached = BTreeSet::new();
+        let mut queue = vec![self.start];
+        while let Some(name) = queue.pop() {
+            if !reached.insert(name) {
+                continue;
+            }
+            match self.references.get(name) {
+                Some(references) => queue.extend(references.iter().rev()),
+                None => errors.push(GrammarError::Undefined(name)),
+            }
+        }
+        errors.extend(
+            self.rules
+                .keys()
+                .copied()
+                .filter(|name| !reached.contains(name))
+                .map(GrammarError::Unused),
+        );
+        errors.extend(self.rules.keys().copied().filter_map(|name| self.cycle(name)));
+        errors
+    }
+
+    /// Parses `name` from empty input, where `Rule` records the cycles it runs into.
+    fn cycle(&self, name: &'static str) -> Option<GrammarError> {
+        let grammar = Grammar {
+            start: name,
+            ..self.clone()
+        };
+        let mut state: State = "".into();
+        grammar.parse(&mut state);
+        if state.cycles.contains(&name) {
+            return Some(GrammarError::Cycle(name));
+        }
+        None
+    }
+}
+
+impl Parser for Grammar {
+    fn parse(&self, state: &mut State) -> Node {
+        let outer = state.grammar.replace(self.rules.clone());
+        let node = Rule(self.start).parse(state);
+        state.grammar = outer;
+        node
+    }
+}
+
+/// Creates references to other rules while a rule of `Grammar` is built.
+#[derive(Debug, Default)]
+pub struct References {
+    names: Vec<&'static str>,
+}
+
+impl References {
+    /// Like `rule`, but the reference is also checked by `Grammar::check`.
+    pub fn rule(&mut self, name: &'static str) -> Rule {
+        self.names.push(name);
+        Rule(name)
+    }
+}
+
+/// Reference to a rule of the parsed `Grammar`, resolved when parsing.
+#[derive(Debug, Clone, Copy)]
+pub struct Rule(&'static str);
+
+pub fn rule(name: &'static str) -> Rule {
+    Rule(name)
+}
+
+impl Parser for Rule {
+    fn parse(&self, state: &mut State) -> Node {
+        let name = self.0;
+        let parser = match state.grammar.as_ref().and_then(|rules| rules.get(name)) {
+            Some(parser) => parser.clone(),
+            None => return raise(GrammarError::Undefined(name), 0).parse(state),
+        };
+
+        // Active rules are sorted by offset, only the ones at the current offset can loop.
+        let offset = state.input.range.0;
+        let cycle = state
+            .active_rules
+            .iter()
+            .rev()
+            .take_while(|(_, at)| *at == offset)
+            .any(|(active, _)| *active == name);
+        if cycle {
+            state.cycles.push(name);
+            return raise(GrammarError::Cycle(name), 0).parse(state);
+        }
+
+        state.active_rules.push((name, offset));
+        let node = parser.parse(state);
+        state.active_rules.pop();
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use test_case::test_case;
+
+    fn list() -> Grammar {
+        Grammar::new("value")
+            .rule("value", |refs| {
+                let list = refs.rule("list");
+                v_node(NodeId("Value"), move |state| match state.peek(1).as_ref() {
+                    "[" => state.add(list),
+                    _ => state.add(recover("x")),
+                })
+            })
+            .rule("list", |refs| {
+                let value = refs.rule("value");
+                node(NodeId("List"), move |state| {
+                    state.add("[");
+                    while !["]", ""].contains(&state.peek(1).as_ref()) {
+                        state.add(value);
+                    }
+                    state.add(recover("]"));
+                })
+            })
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        let parsed = State::parse("[x[x]]", list());
+
+        assert!(parsed.errors.is_empty());
+        assert!(parsed.rest.is_empty());
+        let outer = &parsed.nodes[0];
+        assert!(outer.is(NodeId("List")));
+        assert!(outer.children[2].is(NodeId("List")));
+        assert_eq!(outer.children[2].span.as_ref(), "[x]");
+    }
+
+    #[test]
+    fn builds_rules_once() {
+        let built = Rc::new(Cell::new(0));
+        let counter = built.clone();
+        let grammar = list().rule("value", move |refs| {
+            counter.set(counter.get() + 1);
+            let list = refs.rule("list");
+            v_node(NodeId("Value"), move |state| match state.peek(1).as_ref() {
+                "[" => state.add(list),
+                _ => state.add(recover("x")),
+            })
+        });
+
+        for _ in 0..3 {
+            let parsed = Parse::new(grammar.clone()).run("[[[x]]]");
+            assert!(parsed.errors.is_empty());
+        }
+        assert_eq!(built.get(), 1);
+    }
+
+    #[test]
+    fn cycle_without_progress() {
+        let grammar = Grammar::new("expr").rule("expr", |refs| {
+            let expr = refs.rule("expr");
+            node(NodeId("Expr"), move |state| {
+                state.add(expr);
+                state.add(recover("x"));
+            })
+        });
+        let parsed = State::parse("x", grammar);
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(
+            parsed.errors[0].problem.to_string(),
+            "I found rule `expr` calling itself without consuming input"
+        );
+        assert!(parsed.rest.is_empty());
+    }
+
+    #[test]
+    fn undefined_when_parsing() {
+        let parsed = State::parse("x", rule("value"));
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(
+            parsed.errors[0].problem.to_string(),
+            "I expected rule `value` to be defined"
+        );
+    }
+
+    #[test_case(list(), &[] ; "valid")]
+    #[test_case(list().rule("unused", |refs| refs.rule("value")), &["I found rule `unused` that is never used"] ; "unused")]
+    #[test_case(
+        list().rule("list", |refs| refs.rule("item")),
+        &["I expected rule `item` to be defined"]
+        ; "undefined"
+    )]
+    #[test_case(Grammar::new("value"), &["I expected rule `value` to be defined"] ; "missing start")]
+    #[test_case(
+        list().rule("list", |refs| {
+            let list = refs.rule("list");
+            node(NodeId("List"), move |state| state.add(list))
+        }),
+        &["I found rule `list` calling itself without consuming input"]
+        ; "cycle"
+    )]
+    #[test_case(
+        list().rule("list", |_| raise("I found rule `list` calling itself without consuming input", 0)),
+        &[]
+        ; "same message without cycle"
+    )]
+    #[test_case(
+        Grammar::new("a").rule("a", |refs| refs.rule("b")).rule("b", |refs| refs.rule("a")),
+        &[
+            "I found rule `a` calling itself without consuming input",
+            "I found rule `b` calling itself without consuming input"
+        ]
+        ; "indirect cycle"
+    )]
+    fn check(grammar: Grammar, expected: &[&str]) {
+        let errors = grammar.check();
+        let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(errors, expected);
+    }
+}
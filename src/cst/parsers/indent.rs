@@ -0,0 +1,238 @@
+use crate::*;
+use derive_more::Display;
+use std::cmp::Ordering;
+
+node_ids! {
+    pub Layout:
+        Newline,
+        Indentation,
+        Indent,
+        Dedent
+}
+
+#[derive(Debug, Display, Clone)]
+enum IndentError {
+    #[display(fmt = "I expected new line")]
+    ExpectedNewline,
+
+    #[display(fmt = "I expected indented block")]
+    ExpectedIndent,
+
+    #[display(fmt = "I expected end of indented block")]
+    ExpectedDedent,
+
+    #[display(fmt = "I expected indentation matching one of the outer blocks")]
+    InconsistentDedent,
+
+    #[display(fmt = "I didn't expect tabs and spaces mixed in indentation")]
+    MixedIndent,
+
+    #[display(fmt = "I expected indentation with {} like before", _0)]
+    InconsistentUnit(&'static str),
+}
+
+/// Indent stack and indentation of the current line, see `newline`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Indentation {
+    stack: Vec<usize>,
+    line: usize,
+    unit: Option<&'static str>,
+}
+
+impl Indentation {
+    fn level(&self) -> usize {
+        self.stack.last().copied().unwrap_or_default()
+    }
+}
+
+impl State {
+    /// Compares indentation of the current line with the innermost indented block.
+    pub fn indent_change(&self) -> Ordering {
+        self.indentation.line.cmp(&self.indentation.level())
+    }
+}
+
+fn zero_width(name: NodeId, state: &mut State) -> Node {
+    Node {
+        name,
+        span: state.input.chomp_chars(0),
+        children: vec![],
+        alias: vec![],
+    }
+}
+
+/// Tracks indentation with a fresh indent stack, so the parser can be embedded
+/// in a grammar which doesn't care about indentation.
+pub fn with_indentation(parser: impl Parser) -> impl Parser {
+    move |state: &mut State| {
+        let outer = std::mem::take(&mut state.indentation);
+        let node = parser.parse(state);
+        state.indentation = outer;
+        node
+    }
+}
+
+/// Line endings with following blank lines as `Newline`
+/// and leading whitespace of the next line as `Indentation`.
+///
+/// Measures indentation of the next line for `indent` and `dedent`.
+/// End of input counts as a new line without indentation.
+pub fn newline() -> impl Parser {
+    no_extra(v_node(None, |state| {
+        let checkpoint = state.checkpoint();
+        let blank = state.chomp_while(is_ws);
+        state.rewind(checkpoint);
+
+        let eof = blank.len() == state.input.len();
+        let text = blank.as_ref();
+        let end = match text.rfind('\n') {
+            Some(index) => index + 1,
+            None if eof => text.len(),
+            None => {
+                state.add(raise(IndentError::ExpectedNewline, 0));
+                return;
+            }
+        };
+        state.add(recognize(Layout::Newline, move |state: &mut State| {
+            state.input.chomp_chars(end)
+        }));
+
+        let indentation = &text[end..];
+        state.indentation.line = indentation.chars().count();
+        if indentation.is_empty() {
+            return;
+        }
+
+        let tabs = indentation.contains('\t');
+        let unit = if tabs { "tabs" } else { "spaces" };
+        let problem = match state.indentation.unit {
+            _ if tabs && indentation.contains(' ') => Some(IndentError::MixedIndent),
+            Some(expected) if expected != unit => Some(IndentError::InconsistentUnit(expected)),
+            Some(_) => None,
+            None => {
+                state.indentation.unit = Some(unit);
+                None
+            }
+        };
+        match problem {
+            Some(problem) => state.add(raise(problem, indentation.len())),
+            None => state.add(recognize(Layout::Indentation, inline_ws0())),
+        }
+    }))
+}
+
+/// Zero-width `Indent` when the current line is indented deeper than the innermost block.
+pub fn indent() -> impl Parser {
+    |state: &mut State| {
+        if state.indent_change() != Ordering::Greater {
+            return raise(IndentError::ExpectedIndent, 0).parse(state);
+        }
+        let line = state.indentation.line;
+        state.indentation.stack.push(line);
+        zero_width(Layout::Indent, state)
+    }
+}
+
+/// Zero-width `Dedent` closing the innermost block when the current line is indented less.
+pub fn dedent() -> impl Parser {
+    |state: &mut State| {
+        if state.indent_change() != Ordering::Less {
+            return raise(IndentError::ExpectedDedent, 0).parse(state);
+        }
+        state.indentation.stack.pop();
+        if state.indent_change() == Ordering::Greater {
+            return raise(IndentError::InconsistentDedent, 0).parse(state);
+        }
+        zero_width(Layout::Dedent, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn statement() -> impl Parser {
+        node(NodeId("Statement"), |state| {
+            state.add(ident(NodeId("Name")));
+            if state.peek(1).as_ref() == ":" {
+                state.add(":");
+                state.add(newline());
+                state.add(indent());
+                state.add(block());
+                state.add(dedent());
+            } else {
+                state.add(newline());
+            }
+        })
+    }
+
+    fn block() -> impl Parser {
+        node(NodeId("Block"), |state| loop {
+            let start = state.input.clone();
+            state.add(statement());
+            if state.input == start || state.input.is_empty() {
+                break;
+            }
+            if state.indent_change() == Ordering::Less {
+                break;
+            }
+        })
+    }
+
+    fn names(node: &Node, depth: usize, output: &mut Vec<String>) {
+        for child in &node.children {
+            if child.is(NodeId("Name")) {
+                output.push(format!("{}{}", depth, child.span.as_ref()));
+            }
+            let depth = depth + child.is(NodeId("Block")) as usize;
+            names(child, depth, output);
+        }
+    }
+
+    fn find(node: &Node, name: NodeId) -> Option<&Node> {
+        if node.is(name) {
+            return Some(node);
+        }
+        node.children.iter().filter_map(|c| find(c, name)).next()
+    }
+
+    #[test_case("a\nb", &["0a", "0b"])]
+    #[test_case("a:\n  b\n  c\nd", &["0a", "1b", "1c", "0d"])]
+    #[test_case("a:\n  b:\n    c\n\n  d\n", &["0a", "1b", "2c", "1d"])]
+    #[test_case("a:\n\tb:\n\t\tc\nd", &["0a", "1b", "2c", "0d"])]
+    fn blocks(input: &str, expected: &[&str]) {
+        let parsed = State::parse(input, with_indentation(block()));
+        let mut actual = vec![];
+        names(&parsed.nodes[0], 0, &mut actual);
+
+        assert!(parsed.errors.is_empty());
+        assert!(parsed.rest.is_empty());
+        assert_eq!(parsed.nodes[0].span.as_ref(), input);
+        assert_eq!(actual, expected);
+    }
+
+    #[test_case("a:\nb", "I expected indented block")]
+    #[test_case(
+        "a:\n    b\n  c",
+        "I expected indentation matching one of the outer blocks"
+    )]
+    #[test_case("a:\n \tb", "I didn't expect tabs and spaces mixed in indentation")]
+    #[test_case("a:\n  b:\n\tc", "I expected indentation with spaces like before")]
+    #[test_case("a b", "I expected new line")]
+    fn problems(input: &str, expected: &str) {
+        let parsed = State::parse(input, with_indentation(block()));
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].problem.to_string(), expected);
+    }
+
+    #[test]
+    fn indentation_is_kept() {
+        let parsed = State::parse("a:\n  b", with_indentation(block()));
+        let root = parsed.root();
+        let indentation = find(root, Layout::Indentation).map(|n| n.span.as_ref());
+
+        assert_eq!(indentation, Some("  "));
+    }
+}
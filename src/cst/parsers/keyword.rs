@@ -0,0 +1,194 @@
+use crate::*;
+use derive_more::Display;
+
+#[derive(Debug, Display, Clone)]
+enum KeywordError {
+    #[display(fmt = "I expected `{}`", _0)]
+    UnexpectedKeyword(&'static str),
+
+    #[display(fmt = "I expected an identifier")]
+    ExpectedIdent,
+
+    #[display(fmt = "I didn't expect keyword `{}` here", _0)]
+    ReservedKeyword(&'static str),
+}
+
+/// Like `token`, but the keyword has to end at an identifier boundary,
+/// so `keyword("true")` doesn't accept `trueish`.
+///
+/// Produces `TOKEN` node aliased as `KEYWORD`.
+pub fn keyword(keyword: &'static str) -> impl Parser {
+    move |state: &mut State| {
+        let matching = state.options().matching;
+        let matched_len = matching_len(state, matching, keyword);
+        let keyword_len = matched_len.unwrap_or_else(|| state.steps(keyword));
+        let output = state.peek(keyword_len);
+        let rest = state.peek_nth(keyword_len);
+        let matched = matched_len.is_some();
+        if matched && !is_xid_continue(rest.as_ref()) {
+            state.chomp(keyword_len);
+            return Node::token(output).with_alias(NodeId::KEYWORD);
+        }
+        let len = match output.as_ref() {
+            // Report the whole word, not only the matching prefix.
+            _ if matched => {
+                let checkpoint = state.checkpoint();
+                state.chomp(keyword_len);
+                let len = output.len() + state.chomp_while(is_xid_continue).len();
+                state.rewind(checkpoint);
+                len
+            }
+            _ => output.len(),
+        };
+        raise(KeywordError::UnexpectedKeyword(keyword), len).parse(state)
+    }
+}
+
+pub struct Ident {
+    name: NodeId,
+    keywords: &'static [&'static str],
+    reserved: bool,
+}
+
+/// Identifier starting with `_` or `XID_Start` grapheme followed by `XID_Continue` graphemes.
+///
+/// Words from the keyword table are retagged as `keyword` nodes,
+/// or rejected when keywords are reserved.
+pub fn ident(name: NodeId) -> Ident {
+    Ident {
+        name,
+        keywords: &[],
+        reserved: false,
+    }
+}
+
+impl Ident {
+    pub fn keywords(mut self, keywords: &'static [&'static str]) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    /// Report keywords as errors instead of retagging them.
+    pub fn reserved(mut self, reserved: bool) -> Self {
+        self.reserved = reserved;
+        self
+    }
+}
+
+fn is_ident_start(s: &str) -> bool {
+    s == "_" || is_xid_start(s)
+}
+
+impl Parser<Span> for Ident {
+    fn parse(&self, state: &mut State) -> Span {
+        if !is_ident_start(state.peek(1).as_ref()) {
+            return state.chomp(0);
+        }
+        let start = state.chomp(1);
+        let rest = state.chomp_while(is_xid_continue);
+        let mut output = start;
+        output.range.1 += rest.range.1;
+        output
+    }
+}
+
+impl Parser for Ident {
+    fn parse(&self, state: &mut State) -> Node {
+        let checkpoint = state.checkpoint();
+        let output: Span = Parser::<Span>::parse(self, state);
+        if output.is_empty() {
+            return raise(KeywordError::ExpectedIdent, 0).parse(state);
+        }
+        let matching = state.options().matching;
+        match self
+            .keywords
+            .iter()
+            .find(|k| matches(matching, output.as_ref(), k))
+        {
+            Some(keyword) if self.reserved => {
+                state.rewind(checkpoint);
+                raise(KeywordError::ReservedKeyword(keyword), output.len()).parse(state)
+            }
+            Some(_) => Node::token(output).with_alias(NodeId::KEYWORD),
+            None => Node {
+                name: self.name,
+                span: output,
+                children: vec![],
+                alias: vec![],
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    const KEYWORDS: &[&str] = &["let", "in"];
+
+    #[test_case("true", "true", "")]
+    #[test_case("true)", "true", ")")]
+    #[test_case("true ", "true", " ")]
+    #[test_case("trueish", "", "")]
+    #[test_case("true_", "", "")]
+    #[test_case("tru", "", "")]
+    fn keyword_boundary(input: &str, expected: &str, expected_rest: &str) {
+        let parsed = State::parse(input, keyword("true"));
+
+        let tokens = parsed
+            .nodes
+            .iter()
+            .filter(|n| n.is(NodeId::KEYWORD))
+            .map(|n| n.span.as_ref().to_string())
+            .collect::<Vec<_>>()
+            .concat();
+        assert_eq!(tokens, expected);
+        assert_eq!(parsed.rest.as_ref(), expected_rest);
+        assert_eq!(parsed.errors.is_empty(), !expected.is_empty());
+    }
+
+    #[test_case("letter", false, NodeId("Ident"))]
+    #[test_case("_let", false, NodeId("Ident"))]
+    #[test_case("let", false, NodeId::KEYWORD)]
+    #[test_case("let", true, NodeId::ERROR)]
+    #[test_case("1let", false, NodeId::ERROR)]
+    fn ident_keywords(input: &str, reserved: bool, expected: NodeId) {
+        let parser = ident(NodeId("Ident")).keywords(KEYWORDS).reserved(reserved);
+        let parsed = State::parse(input, parser);
+
+        assert!(parsed.nodes[0].is(expected));
+    }
+
+    #[test]
+    fn case_insensitive_keywords() {
+        let parser = ident(NodeId("Ident")).keywords(KEYWORDS);
+        let parsed = Parse::new(parser).case_insensitive(true).run("LET");
+
+        assert!(parsed.nodes[0].is(NodeId::KEYWORD));
+        assert_eq!(parsed.nodes[0].span.as_ref(), "LET");
+    }
+
+    #[test]
+    fn normalized_keyword_in_chars() {
+        let parsed = Parse::new(keyword("caf\u{e9}"))
+            .normalized(true)
+            .unit(Unit::Char)
+            .run("cafe\u{301} x");
+
+        assert!(parsed.nodes[0].is(NodeId::KEYWORD));
+        assert_eq!(parsed.nodes[0].span.as_ref(), "cafe\u{301}");
+    }
+
+    #[test]
+    fn reserved_keyword_problem() {
+        let parser = ident(NodeId("Ident")).keywords(KEYWORDS).reserved(true);
+        let parsed = State::parse("in", parser);
+
+        assert_eq!(parsed.errors[0].span.as_ref(), "in");
+        assert_eq!(
+            parsed.errors[0].problem.to_string(),
+            "I didn't expect keyword `in` here"
+        );
+    }
+}
@@ -1,11 +1,21 @@
 use crate::*;
 use derive_more::Display;
-use unicode_segmentation::UnicodeSegmentation;
+use std::rc::Rc;
+use unicode_normalization::UnicodeNormalization;
 
 #[derive(Debug, Display, Clone)]
 enum LexerError {
     #[display(fmt = "I expected `{}`", _0)]
     UnexpectedToken(&'static str),
+
+    #[display(fmt = "I didn't expect `{}`", _0)]
+    Unexpected(String),
+
+    #[display(fmt = "I didn't expect end of input")]
+    UnexpectedEOF,
+
+    #[display(fmt = "I expected {} {}", _0, _1)]
+    ExpectedSteps(usize, &'static str),
 }
 
 pub mod utf {
@@ -53,7 +63,7 @@ pub mod utf {
 
     pub fn peek_nth(len: usize) -> impl Parser<Span> {
         move |state: &mut State| {
-            let mut iter = state.input.graphemes_idx();
+            let mut iter = state.cursor();
 
             let (offset, len) = iter.nth(len).map(|(o, g)| (o, g.len())).unwrap_or_default();
 
@@ -81,7 +91,7 @@ pub mod utf {
 
     pub fn peek(len: usize) -> impl Parser<Span> {
         move |state: &mut State| {
-            let iter = state.input.graphemes_idx();
+            let iter = state.cursor();
             let (offset, grapheme) = iter.take(len).last().unwrap_or_default();
             let mut output = state.input.clone();
             let len = offset + grapheme.len();
@@ -101,13 +111,11 @@ pub mod utf {
 
     pub fn chomp_if(f: impl Fn(&str) -> bool) -> impl Parser<Span> {
         move |state: &mut State| {
-            let current = peek_nth(0).parse(state);
-            let current = match current.as_ref() {
-                "" => return chomp(0).parse(state),
-                c => c,
+            let len = match state.cursor().next() {
+                Some((_, current)) if f(current) => current.len(),
+                _ => 0,
             };
-            let len = if f(current) { 1 } else { 0 };
-            chomp(len).parse(state)
+            state.input.chomp_chars(len)
         }
     }
 
@@ -121,20 +129,84 @@ pub mod utf {
 
     pub fn chomp_while(f: impl Fn(&str) -> bool) -> impl Parser<Span> {
         move |state: &mut State| {
-            let mut len = 0usize;
-            loop {
-                let current = peek_nth(len).parse(state);
-                let current = match current.as_ref() {
-                    "" => return chomp(len).parse(state),
-                    c => c,
-                };
-                let valid = f(current);
-                if valid {
-                    len += 1;
-                } else {
-                    return chomp(len).parse(state);
+            let mut cursor = state.cursor();
+            let len = loop {
+                let offset = cursor.offset();
+                match cursor.next() {
+                    Some((_, current)) if f(current) => (),
+                    _ => break offset,
                 }
+            };
+            state.input.chomp_chars(len)
+        }
+    }
+
+    /// Keeps `problem` for `recognize` to raise, the tree and errors are left untouched.
+    /// Nothing is consumed, so the returned span is empty.
+    fn fail(state: &mut State, problem: impl Problem + 'static) -> Span {
+        state.failure = Some(Failure {
+            problem: Rc::new(problem),
+            context: vec![],
+        });
+        chomp(0).parse(state)
+    }
+
+    fn unexpected(state: &mut State) -> Span {
+        let next = peek(1).parse(state);
+        match next.as_ref() {
+            "" => fail(state, LexerError::UnexpectedEOF),
+            c => fail(state, LexerError::Unexpected(c.to_string())),
+        }
+    }
+
+    /// Like `chomp_while`, but fails with a problem when nothing matches.
+    /// `recognize` raises it like `raise(problem, 0)`. Use `label` to report your own one.
+    pub fn chomp_while1(f: impl Fn(&str) -> bool) -> impl Parser<Span> {
+        let parser = chomp_while(f);
+        move |state: &mut State| match parser.parse(state) {
+            output if !output.is_empty() => output,
+            _ => unexpected(state),
+        }
+    }
+
+    /// Like `chomp_if`, but fails with a problem when the next grapheme doesn't match.
+    pub fn chomp_if1(f: impl Fn(&str) -> bool) -> impl Parser<Span> {
+        let parser = chomp_if(f);
+        move |state: &mut State| match parser.parse(state) {
+            output if !output.is_empty() => output,
+            _ => unexpected(state),
+        }
+    }
+
+    /// Chomps exactly `len` steps, `Unit`s or tokens of the active lexer,
+    /// or fails with a problem when the input is shorter.
+    pub fn chomp_n(len: usize) -> impl Parser<Span> {
+        move |state: &mut State| {
+            if state.cursor().take(len).count() < len {
+                let unit = match state.options().unit {
+                    _ if state.tokens.is_some() => "tokens",
+                    Unit::Grapheme => "graphemes",
+                    Unit::Char => "code points",
+                };
+                return fail(state, LexerError::ExpectedSteps(len, unit));
             }
+            chomp(len).parse(state)
+        }
+    }
+
+    /// Chomps everything before `token`, which is compared exactly and not consumed.
+    /// Fails with a problem when `token` never comes.
+    pub fn take_until_token(token: &'static str) -> impl Parser<Span> {
+        move |state: &mut State| {
+            let len = match state.input.as_ref().find(token) {
+                Some(len) => len,
+                None => return fail(state, LexerError::UnexpectedToken(token)),
+            };
+            let mut output = state.input.clone();
+            output.range.1 = len;
+            state.input.range.0 += len;
+            state.input.range.1 -= len;
+            output
         }
     }
 
@@ -171,6 +243,10 @@ pub mod utf {
         #[test_case(chomp(99), "a\u{310}e\u{301}o\u{308}\u{332}\r\n", "")]
         #[test_case(chomp_while(|c| { c != "\n" && c != "\r\n" }),               "a\u{310}e\u{301}o\u{308}\u{332}",  "\r\n")]
         #[test_case(chomp_until(|c| { c == "\n" || c == "\r\n" }),               "a\u{310}e\u{301}o\u{308}\u{332}",  "\r\n")]
+        #[test_case(chomp_while1(|c| c != "\r\n"), "a\u{310}e\u{301}o\u{308}\u{332}", "\r\n")]
+        #[test_case(chomp_if1(|c| c == "a\u{310}"), "a\u{310}", "e\u{301}o\u{308}\u{332}\r\n")]
+        #[test_case(chomp_n(2), "a\u{310}e\u{301}", "o\u{308}\u{332}\r\n")]
+        #[test_case(take_until_token("\r\n"), "a\u{310}e\u{301}o\u{308}\u{332}", "\r\n")]
         fn test_parser(p: impl Parser<Span>, expected: &'static str, expected_rest: &'static str) {
             let mut state: State = INPUT.into();
             let actual = p.parse(&mut state);
@@ -178,6 +254,93 @@ pub mod utf {
             let rest = state.input;
             assert_eq!(rest.as_ref(), expected_rest);
         }
+
+        #[test_case(chomp_while1(|c| c == "x"), "I didn't expect `a\u{310}`")]
+        #[test_case(chomp_if1(|c| c == "x"), "I didn't expect `a\u{310}`")]
+        #[test_case(chomp_n(9), "I expected 9 graphemes")]
+        #[test_case(take_until_token("x"), "I expected `x`")]
+        fn reports_problem(p: impl Parser<Span>, problem: &str) {
+            let mut state: State = INPUT.into();
+            let actual = p.parse(&mut state);
+            assert!(actual.is_empty());
+            assert_eq!(state.input.as_ref(), INPUT);
+            assert!(state.errors.is_empty());
+            assert!(!state.panic);
+            assert!(state.nodes[0].children.is_empty());
+            let failure = state.failure.expect("Failure");
+            assert_eq!(failure.problem.to_string(), problem);
+        }
+
+        #[test]
+        fn recognize_raises_problem() {
+            let mut state: State = INPUT.into();
+            state.add(recognize(NodeId("Text"), chomp_while1(|c| c == "x")));
+
+            assert_eq!(state.errors.len(), 1);
+            assert_eq!(
+                state.errors[0].problem.to_string(),
+                "I didn't expect `a\u{310}`"
+            );
+            assert_eq!(state.errors[0].span.range, (0, 0));
+            assert!(state.panic);
+            assert!(state.nodes[0].children[0].is(NodeId::ERROR));
+        }
+
+        #[test_case(chomp_if1(digit().into_fn()).many1(), "123")]
+        #[test_case(chomp_while1(digit().into_fn()).or(chomp_while1(alpha().into_fn())), "abc")]
+        fn recovered_failure_is_not_raised(p: impl Parser<Span>, input: &str) {
+            let parsed = State::parse(input, recognize(NodeId("D"), p));
+
+            assert!(parsed.errors.is_empty());
+            assert_eq!(parsed.nodes.len(), 1);
+            assert!(parsed.nodes[0].is(NodeId("D")));
+            assert_eq!(parsed.nodes[0].span.as_ref(), input);
+        }
+
+        #[test]
+        fn chomp_n_counts_units() {
+            let mut state: State = "e\u{301}".into();
+            state.options.unit = Unit::Char;
+            state.add(recognize(NodeId("Text"), chomp_n(3)));
+
+            assert_eq!(
+                state.errors[0].problem.to_string(),
+                "I expected 3 code points"
+            );
+        }
+
+        #[test]
+        fn extends_error_in_panic_mode() {
+            let mut state: State = "ab".into();
+            state.add(raise(LexerError::UnexpectedEOF, 1));
+            state.add(recognize(NodeId("Digit"), chomp_if1(is_digits)));
+
+            assert_eq!(state.errors.len(), 1);
+            assert_eq!(state.nodes[0].children.len(), 1);
+            assert_eq!(state.nodes[0].children[0].span.as_ref(), "a");
+        }
+
+        #[test]
+        fn reports_once_in_panic_mode() {
+            let mut state: State = "".into();
+            state.add(recognize(NodeId("WS"), chomp_while1(is_ws)));
+            state.add(recognize(NodeId("Digit"), chomp_if1(is_digits)));
+
+            assert_eq!(state.errors.len(), 1);
+            assert_eq!(
+                state.errors[0].problem.to_string(),
+                "I didn't expect end of input"
+            );
+        }
+
+        #[test]
+        fn own_problem() {
+            let mut state: State = "x".into();
+            let number = chomp_while1(is_digits).label("I expected a number");
+            state.add(recognize(NodeId("Number"), number));
+
+            assert_eq!(state.errors[0].problem.to_string(), "I expected a number");
+        }
     }
 }
 
@@ -189,63 +352,238 @@ impl Parser for &'static str {
 
 impl Parser<Span> for &'static str {
     fn parse(&self, state: &mut State) -> Span {
-        let token_len = self.graphemes(true).count();
+        let matching = state.options().matching;
+        let len = matching_len(state, matching, self).unwrap_or_default();
+        utf::chomp(len).parse(state)
+    }
+}
 
-        let output = utf::peek(token_len).parse(state);
-        match output {
-            n if n.as_ref() == *self => utf::chomp(token_len).parse(state),
-            _ => utf::chomp(0).parse(state),
-        }
+fn fold(matching: Matching, s: &str) -> String {
+    let s: String = if matching.normalized {
+        s.nfc().collect()
+    } else {
+        s.to_string()
+    };
+    if matching.case_insensitive {
+        s.to_lowercase()
+    } else {
+        s
     }
 }
 
-pub fn token(token: &'static str) -> impl Parser {
-    let token_len = token.graphemes(true).count();
+/// Compares `source` with `token` respecting `Matching` options.
+pub(crate) fn matches(matching: Matching, source: &str, token: &str) -> bool {
+    if source == token {
+        return true;
+    }
+    if matching == Matching::default() {
+        return false;
+    }
+    fold(matching, source) == fold(matching, token)
+}
 
+/// Number of steps at the start of input matching `token`, respecting `Matching` options.
+///
+/// Normalized input can take more steps than `token`, like `cafe\u{301}` for `caf\u{e9}`
+/// in `Unit::Char`, and tokens of a lexer depend on what follows `token` in the input,
+/// so such input is scanned until the folded prefix matches or gets longer.
+pub(crate) fn matching_len(state: &State, matching: Matching, token: &str) -> Option<usize> {
+    let input = state.input.as_ref();
+    let ends = state.cursor().map(|(offset, step)| offset + step.len());
+    if !matching.normalized && state.tokens.is_none() {
+        let len = state.steps(token);
+        let end = ends.take(len).last().unwrap_or_default();
+        return Some(len).filter(|_| matches(matching, &input[..end], token));
+    }
+    let token = fold(matching, token);
+    if token.is_empty() {
+        return Some(0);
+    }
+    let token_len = token.chars().count();
+    for (index, end) in ends.enumerate() {
+        let prefix = fold(matching, &input[..end]);
+        if prefix == token {
+            return Some(index + 1);
+        }
+        if prefix.chars().count() > token_len {
+            break;
+        }
+    }
+    None
+}
+
+fn token_with(token: &'static str, case_insensitive: bool) -> impl Parser {
     move |state: &mut State| {
-        let output = utf::peek(token_len).parse(state);
-        match output {
-            n if n.as_ref() == token => {
-                utf::chomp(token_len).parse(state);
-                Node::token(n)
+        let mut matching = state.options().matching;
+        matching.case_insensitive |= case_insensitive;
+
+        match matching_len(state, matching, token) {
+            Some(len) => Node::token(utf::chomp(len).parse(state)),
+            None => {
+                let len = state.peek(state.steps(token)).len();
+                raise(LexerError::UnexpectedToken(token), len).parse(state)
             }
-            n => raise(LexerError::UnexpectedToken(token), n.len()).parse(state),
         }
     }
 }
 
+/// Parses `parser` stepping over `unit` instead of the one from `ParseOptions`,
+/// e.g. code points inside of a JSON string.
+pub fn with_unit(unit: Unit, parser: impl Parser) -> impl Parser {
+    move |state: &mut State| {
+        let outer = std::mem::replace(&mut state.options.unit, unit);
+        let node = parser.parse(state);
+        state.options.unit = outer;
+        node
+    }
+}
+
+pub fn token(token: &'static str) -> impl Parser {
+    token_with(token, false)
+}
+
+/// Like `token`, but ignores case regardless of `ParseOptions`.
+/// Produced node keeps the original source text.
+pub fn token_ci(token: &'static str) -> impl Parser {
+    token_with(token, true)
+}
+
+/// Like `recognize`, but raises `problem` when nothing was recognized
+/// and `parser` didn't fail with its own one.
 pub fn recognize1(
     name: NodeId,
     parser: impl Parser<Span>,
     problem: impl Problem + Clone + 'static,
 ) -> impl Parser {
     move |state: &mut State| {
+        state.failure = None;
         let output = parser.parse(state);
 
-        match output {
-            result if !result.is_empty() => Node {
+        match (output, state.failure.take()) {
+            (result, _) if !result.is_empty() => Node {
                 name,
                 span: result,
                 children: vec![],
                 alias: vec![],
             },
+            (_, Some(failure)) => raise_failure(state, failure),
             _ => raise(problem.clone(), 0).parse(state),
         }
     }
 }
 
+/// Node of text recognized by `parser`.
+/// Raises the problem `parser` failed with, e.g. `chomp_while1`, when nothing was recognized.
 pub fn recognize(name: NodeId, parser: impl Parser<Span>) -> impl Parser {
     move |state: &mut State| {
+        state.failure = None;
         let output = parser.parse(state);
 
-        match output {
-            result if !result.is_empty() => Node {
+        match (output, state.failure.take()) {
+            (result, _) if !result.is_empty() => Node {
                 name,
                 span: result,
                 children: vec![],
                 alias: vec![],
             },
+            (_, Some(failure)) => raise_failure(state, failure),
             _ => none().parse(state),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(token_ci("select"), "SELECT", "SELECT")]
+    #[test_case(token_ci("select"), "Select", "Select")]
+    #[test_case(token_ci("select"), "selec", "")]
+    #[test_case(token("select"), "Select", "")]
+    fn token_case(parser: impl Parser, input: &str, expected: &str) {
+        let parsed = State::parse(input, parser);
+        let actual = if parsed.errors.is_empty() {
+            parsed.nodes[0].span.as_ref()
+        } else {
+            ""
+        };
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test_case("caf\u{e9}", "caf\u{e9}")]
+    #[test_case("cafe\u{301}", "cafe\u{301}")]
+    #[test_case("CAFE\u{301}", "CAFE\u{301}")]
+    fn token_normalized(input: &str, expected: &str) {
+        let parsed = Parse::new(token("caf\u{e9}"))
+            .normalized(true)
+            .case_insensitive(true)
+            .run(input);
+
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.nodes[0].span.as_ref(), expected);
+    }
+
+    #[test_case("caf\u{e9}", "cafe\u{301}!", "cafe\u{301}")]
+    #[test_case("cafe\u{301}", "caf\u{e9}!", "caf\u{e9}")]
+    #[test_case("caf\u{e9}", "CAFE\u{301}", "CAFE\u{301}")]
+    fn token_normalized_chars(token: &'static str, input: &str, expected: &str) {
+        let parsed = Parse::new(token_ci(token))
+            .normalized(true)
+            .unit(Unit::Char)
+            .run(input);
+
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.nodes[0].span.as_ref(), expected);
+    }
+
+    #[test]
+    fn token_not_normalized() {
+        let parsed = State::parse("cafe\u{301}", token("caf\u{e9}"));
+
+        assert_eq!(parsed.errors.len(), 1);
+    }
+
+    #[test_case(Unit::Grapheme, utf::chomp(1), "e\u{301}x", "e\u{301}")]
+    #[test_case(Unit::Char, utf::chomp(1), "e\u{301}x", "e")]
+    #[test_case(Unit::Grapheme, utf::chomp_while(is_ws), " \r\n\tx", " \r\n\t")]
+    #[test_case(Unit::Char, utf::chomp_while(is_ws), " \r\n\tx", " \r\n\t")]
+    #[test_case(
+        Unit::Char,
+        utf::chomp_until(is_line_ending),
+        "a\u{301}\r\nb",
+        "a\u{301}"
+    )]
+    #[test_case(Unit::Char, utf::chomp(1), "\r\nx", "\r\n")]
+    fn units(unit: Unit, parser: impl Parser<Span>, input: &str, expected: &str) {
+        let mut state: State = input.into();
+        state.options.unit = unit;
+        let actual = parser.parse(&mut state);
+
+        assert_eq!(actual.as_ref(), expected);
+    }
+
+    // `"\u{301}` is one grapheme, so the quote is found only when stepping over chars.
+    #[test_case(Unit::Grapheme, 1)]
+    #[test_case(Unit::Char, 0)]
+    fn with_unit_quote(unit: Unit, errors: usize) {
+        let string = node(NodeId("String"), |state| {
+            state.add("\"");
+            state.add(recognize(NodeId("Text"), chomp_until(|c| c == "\"")));
+            state.add(recover("\""));
+        });
+        let parsed = State::parse("\"\u{301}\"", with_unit(unit, string));
+
+        assert_eq!(parsed.errors.len(), errors);
+        assert_eq!(parsed.nodes[0].span.as_ref(), "\"\u{301}\"");
+    }
+
+    #[test]
+    fn unit_option() {
+        let parsed = Parse::new(token("e")).unit(Unit::Char).run("e\u{301}");
+
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.rest.as_ref(), "\u{301}");
+    }
+}
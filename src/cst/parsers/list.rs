@@ -0,0 +1,190 @@
+use crate::*;
+use derive_more::Display;
+
+#[derive(Debug, Display, Clone)]
+enum ListError {
+    #[display(fmt = "I didn't expect trailing `{}` before `{}`", _0, _1)]
+    TrailingSeparator(&'static str, &'static str),
+
+    #[display(fmt = "I expected at least one item before `{}`", _0)]
+    Empty(&'static str),
+}
+
+pub struct DelimitedList<P, Pr> {
+    open: &'static str,
+    item: P,
+    separator: &'static str,
+    close: &'static str,
+    problem: Pr,
+    trailing: bool,
+    empty: bool,
+}
+
+/// List of `item`s separated by `separator` between `open` and `close`,
+/// like `[true, false]`.
+///
+/// Junk between items and missing `close` are reported with `problem`.
+/// Missing separator is reported with `problem` as well, but the next item is still parsed.
+/// Trailing separator and empty list are allowed by default.
+pub fn delimited_list<P, Pr>(
+    open: &'static str,
+    item: P,
+    separator: &'static str,
+    close: &'static str,
+    problem: Pr,
+) -> DelimitedList<P, Pr>
+where
+    P: Parser,
+    Pr: Problem + Clone + 'static,
+{
+    DelimitedList {
+        open,
+        item,
+        separator,
+        close,
+        problem,
+        trailing: true,
+        empty: true,
+    }
+}
+
+impl<P, Pr> DelimitedList<P, Pr> {
+    pub fn trailing(mut self, allowed: bool) -> Self {
+        self.trailing = allowed;
+        self
+    }
+
+    pub fn empty(mut self, allowed: bool) -> Self {
+        self.empty = allowed;
+        self
+    }
+}
+
+fn peek_is(state: &mut State, token: &str) -> bool {
+    let len = state.steps(token);
+    state.peek(len).as_ref() == token
+}
+
+impl<P, Pr> Parser for DelimitedList<P, Pr>
+where
+    P: Parser,
+    Pr: Problem + Clone + 'static,
+{
+    fn parse(&self, state: &mut State) -> Node {
+        v_node(None, |state| {
+            state.add(self.open);
+            if peek_is(state, self.close) {
+                if !self.empty {
+                    state.add(raise(ListError::Empty(self.close), 0));
+                }
+                state.add(recover(self.close));
+                return;
+            }
+            let mut missing_separator = false;
+            'outer: loop {
+                let start = state.input.clone();
+                let failed = try_add(state, &self.item);
+                if missing_separator && !failed && state.input != start {
+                    state.panic = false;
+                }
+                missing_separator = false;
+                'inner: loop {
+                    // Until we find either close or separator
+                    if peek_is(state, self.close) {
+                        break 'outer;
+                    }
+                    if peek_is(state, self.separator) {
+                        let checkpoint = state.checkpoint();
+                        state.add(recover(self.separator));
+                        if peek_is(state, self.close) {
+                            if !self.trailing {
+                                state.rewind(checkpoint);
+                                let len = self.separator.len();
+                                let problem =
+                                    ListError::TrailingSeparator(self.separator, self.close);
+                                state.add(raise(problem, len));
+                            }
+                            break 'outer;
+                        }
+                        break 'inner;
+                    }
+                    let next = state.peek(1);
+                    match next.as_ref() {
+                        "" => {
+                            // EOF
+                            state.add(raise(self.problem.clone(), 0));
+                            break 'outer;
+                        }
+                        _ if !state.panic => {
+                            // Missing separator, maybe next item begins here
+                            state.add(raise(self.problem.clone(), 0));
+                            missing_separator = true;
+                            break 'inner;
+                        }
+                        _ => state.add(raise(self.problem.clone(), next.len())),
+                    }
+                }
+            }
+            state.add(recover(self.close));
+        })
+        .parse(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn list(trailing: bool, empty: bool) -> impl Parser {
+        let extra = recognize(NodeId("WS"), chomp_while(is_ws)).arc();
+        let item = recognize1(
+            NodeId("Number"),
+            chomp_while(is_digits),
+            "I expected a number",
+        );
+        let list = delimited_list("[", item, ",", "]", "I expected `,` or `]`")
+            .trailing(trailing)
+            .empty(empty);
+        with_extra(
+            extra,
+            node(NodeId("List"), move |state| {
+                state.add(|state: &mut State| list.parse(state))
+            }),
+        )
+    }
+
+    #[test_case("[]", true, true, &[])]
+    #[test_case("[1, 2, 3]", true, true, &[])]
+    #[test_case("[1, 2, ]", true, true, &[])]
+    #[test_case("[ ]", true, false, &["I expected at least one item before `]`"])]
+    #[test_case("[1, 2, ]", false, true, &["I didn't expect trailing `,` before `]`"])]
+    #[test_case("[1 2]", true, true, &["I expected `,` or `]`"])]
+    #[test_case("[1, 2", true, true, &["I expected `,` or `]`"])]
+    #[test_case("[1, x2, 3]", true, true, &["I expected a number"])]
+    #[test_case("[1 x, 3]", true, true, &["I expected `,` or `]`"])]
+    #[test_case("[1 \u{e9}\u{e9}, 3]", true, true, &["I expected `,` or `]`"])]
+    fn problems(input: &str, trailing: bool, empty: bool, expected: &[&str]) {
+        let parsed = State::parse(input, list(trailing, empty));
+
+        let actual = parsed
+            .errors
+            .iter()
+            .map(|e| e.problem.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+        assert!(parsed.rest.is_empty());
+    }
+
+    #[test]
+    fn missing_separator_keeps_items() {
+        let parsed = State::parse("[1 2 3]", list(true, true));
+        let numbers = parsed.nodes[0]
+            .children
+            .iter()
+            .filter(|n| n.is(NodeId("Number")))
+            .count();
+
+        assert_eq!(numbers, 3);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::*;
+use derive_more::Display;
+use std::cell::Cell;
+
+node_ids! {
+    pub Lit:
+        BlockComment,
+        RawString,
+        Heredoc,
+        Delimiter,
+        Label,
+        Content
+}
+
+#[derive(Debug, Display, Clone)]
+enum LiteralError {
+    #[display(fmt = "I expected `{}` closing this `{}`", _0, _1)]
+    UnterminatedComment(&'static str, &'static str),
+
+    #[display(fmt = "I expected `{}` closing this raw string", _0)]
+    UnterminatedRawString(String),
+
+    #[display(fmt = "I expected `{}` line closing this heredoc", _0)]
+    UnterminatedHeredoc(String),
+
+    #[display(fmt = "I expected `\"` after `{}`", _0)]
+    ExpectedQuote(String),
+
+    #[display(fmt = "I expected heredoc label after `{}`", _0)]
+    ExpectedLabel(&'static str),
+
+    #[display(fmt = "I expected new line after heredoc label")]
+    ExpectedNewline,
+}
+
+fn starts_with(state: &State, s: &str) -> bool {
+    state.input.as_ref().starts_with(s)
+}
+
+/// Chomps `len` bytes as node `name`.
+fn bytes(name: NodeId, len: usize) -> impl Parser {
+    recognize(name, move |state: &mut State| state.input.chomp_chars(len))
+}
+
+/// Reports `problem` at the `opener` and adds empty `ERROR` node where the input ended.
+fn unterminated(state: &mut State, problem: LiteralError, opener: Span) {
+    state.panic_error = report(state, problem, opener);
+    state.panic = true;
+    let end = state.input.chomp_chars(0);
+    state.add_node(Node::error(end));
+}
+
+/// Comment between `open` and `close`, like `/* foo */`.
+///
+/// With `nested` enabled, inner comments have to be closed as well, like in Rust.
+/// Unterminated comment is reported at its opening delimiter.
+pub fn block_comment(open: &'static str, close: &'static str, nested: bool) -> impl Parser {
+    no_extra(move |state: &mut State| comment(state, open, close, nested).0)
+}
+
+/// Returns the comment node and whether it was closed.
+fn comment(
+    state: &mut State,
+    open: &'static str,
+    close: &'static str,
+    nested: bool,
+) -> (Node, bool) {
+    let closed = Cell::new(false);
+    let node = node(Lit::BlockComment, |state| {
+        let opener = state.peek(state.steps(open));
+        if !starts_with(state, open) {
+            state.add(token(open));
+            return;
+        }
+        state.add(bytes(Lit::Delimiter, open.len()));
+        loop {
+            let text = state.input.as_ref();
+            if text.starts_with(close) {
+                state.add(bytes(Lit::Delimiter, close.len()));
+                closed.set(true);
+                return;
+            }
+            if nested && text.starts_with(open) {
+                let (inner, inner_closed) = comment(state, open, close, nested);
+                state.add_node(inner);
+                if !inner_closed {
+                    // Already reported at the inner opening delimiter.
+                    return;
+                }
+                continue;
+            }
+            if text.is_empty() {
+                let problem = LiteralError::UnterminatedComment(close, open);
+                unterminated(state, problem, opener);
+                return;
+            }
+            let len = text
+                .char_indices()
+                .map(|(index, _)| index)
+                .find(|index| {
+                    let rest = &text[*index..];
+                    rest.starts_with(close) || (nested && rest.starts_with(open))
+                })
+                .unwrap_or(text.len());
+            state.add(bytes(Lit::Content, len));
+        }
+    })
+    .parse(state);
+    (node, closed.get())
+}
+
+/// Raw string without escapes, like `r#"foo "bar""#` for `prefix` `r`.
+///
+/// Any number of `#` can be used between `prefix` and `"`,
+/// the string ends at `"` followed by the same number of `#`.
+/// Unterminated string is reported at its opening delimiter.
+pub fn raw_string(prefix: &'static str) -> impl Parser {
+    no_extra(node(Lit::RawString, move |state| {
+        let text = state.input.as_ref();
+        if !text.starts_with(prefix) {
+            state.add(token(prefix));
+            return;
+        }
+        let hashes = text[prefix.len()..]
+            .chars()
+            .take_while(|c| *c == '#')
+            .count();
+        let open_len = prefix.len() + hashes;
+        if !text[open_len..].starts_with('"') {
+            let problem = LiteralError::ExpectedQuote(text[..open_len].to_string());
+            state.add(raise(problem, open_len));
+            return;
+        }
+        let closing = format!("\"{}", "#".repeat(hashes));
+        let content = text[open_len + 1..].find(&closing);
+
+        let mut opener = state.input.clone();
+        opener.range.1 = open_len + 1;
+        state.add(bytes(Lit::Delimiter, open_len + 1));
+        match content {
+            Some(len) => {
+                state.add(bytes(Lit::Content, len));
+                state.add(bytes(Lit::Delimiter, closing.len()));
+            }
+            None => {
+                let len = state.input.len();
+                state.add(bytes(Lit::Content, len));
+                unterminated(state, LiteralError::UnterminatedRawString(closing), opener);
+            }
+        }
+    }))
+}
+
+/// Heredoc like `<<EOF` for `marker` `<<`, followed by lines until a line equal to the label.
+///
+/// Unterminated heredoc is reported at its opening delimiter.
+pub fn heredoc(marker: &'static str) -> impl Parser {
+    no_extra(node(Lit::Heredoc, move |state| {
+        let mut opener = state.input.clone();
+        if !starts_with(state, marker) {
+            state.add(token(marker));
+            return;
+        }
+        state.add(marker);
+        let label = state.peek(1);
+        if !is_xid_start(label.as_ref()) && label.as_ref() != "_" {
+            state.add(raise(LiteralError::ExpectedLabel(marker), 0));
+            return;
+        }
+        let label = state.chomp_while(is_xid_continue);
+        state.add_node(Node {
+            name: Lit::Label,
+            span: label.clone(),
+            children: vec![],
+            alias: vec![],
+        });
+        let line_ending = state.peek(1);
+        if !is_line_ending(line_ending.as_ref()) {
+            state.add(raise(LiteralError::ExpectedNewline, 0));
+            return;
+        }
+
+        let text = &state.input.as_ref()[line_ending.len()..];
+        let mut offset = 0;
+        let end = loop {
+            let rest = &text[offset..];
+            if rest.is_empty() {
+                break None;
+            }
+            let line_len = rest.find('\n').map(|index| index + 1).unwrap_or(rest.len());
+            let line = rest[..line_len].trim_end_matches(&['\n', '\r'][..]);
+            if line == label.as_ref() {
+                break Some(offset);
+            }
+            offset += line_len;
+        };
+
+        opener.range.1 = marker.len() + label.len();
+        match end {
+            Some(len) => {
+                state.add(bytes(Lit::Content, line_ending.len() + len));
+                state.add(bytes(Lit::Label, label.len()));
+            }
+            None => {
+                let len = state.input.len();
+                state.add(bytes(Lit::Content, len));
+                let problem = LiteralError::UnterminatedHeredoc(label.as_ref().to_string());
+                unterminated(state, problem, opener);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn errors(parsed: &Parsed) -> Vec<(String, String)> {
+        parsed
+            .errors
+            .iter()
+            .map(|e| (e.span.as_ref().to_string(), e.problem.to_string()))
+            .collect()
+    }
+
+    #[test_case(block_comment("/*", "*/", false), "/* a */ b", " b")]
+    #[test_case(block_comment("/*", "*/", false), "/* a /* b */ c */", " c */")]
+    #[test_case(block_comment("/*", "*/", true), "/* a /* b */ c */", "")]
+    #[test_case(block_comment("(*", "*)", true), "(* (* *) *)(* *)", "(* *)")]
+    #[test_case(raw_string("r"), r###"r#"a "b""#c"###, "c")]
+    #[test_case(raw_string("r"), r###"r##"a "# b"##"###, "")]
+    #[test_case(raw_string("r"), r#"r"a""#, "")]
+    #[test_case(heredoc("<<"), "<<EOF\nfoo\nEOFX\nEOF\nrest", "\nrest")]
+    #[test_case(heredoc("<<"), "<<END\r\n\r\nEND", "")]
+    fn recognized(parser: impl Parser, input: &str, rest: &str) {
+        let parsed = State::parse(input, parser);
+
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.rest.as_ref(), rest);
+    }
+
+    #[test_case(
+        block_comment("/*", "*/", true),
+        "x /* a /* b */ c",
+        "/*",
+        "I expected `*/` closing this `/*`"
+    )]
+    #[test_case(
+        block_comment("/*", "*/", true),
+        "x /* a /* b",
+        "/*",
+        "I expected `*/` closing this `/*`"
+    )]
+    #[test_case(
+        raw_string("r"),
+        "x r#\"a\"",
+        "r#\"",
+        "I expected `\"#` closing this raw string"
+    )]
+    #[test_case(raw_string("r"), "x r#a", "r#", "I expected `\"` after `r#`")]
+    #[test_case(
+        heredoc("<<"),
+        "x <<EOF\nfoo\n",
+        "<<EOF",
+        "I expected `EOF` line closing this heredoc"
+    )]
+    #[test_case(heredoc("<<"), "x << EOF", "", "I expected heredoc label after `<<`")]
+    fn problems(parser: impl Parser, input: &str, span: &str, problem: &str) {
+        let parser = node(NodeId("Root"), move |state| {
+            state.add("x ");
+            state.add(|state: &mut State| parser.parse(state));
+        });
+        let parsed = State::parse(input, parser);
+
+        assert_eq!(
+            errors(&parsed),
+            vec![(span.to_string(), problem.to_string())]
+        );
+    }
+
+    #[test]
+    fn unterminated_points_at_innermost_opening() {
+        let parsed = State::parse("/* a /* b", block_comment("/*", "*/", true));
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].span.range, (5, 2));
+        assert!(parsed.rest.is_empty());
+    }
+
+    #[test_case(block_comment("/*", "*/", true), "/* a")]
+    #[test_case(raw_string("r"), "r#\"a\"")]
+    #[test_case(heredoc("<<"), "<<EOF\nfoo\n")]
+    fn unterminated_ends_with_error(parser: impl Parser, input: &str) {
+        let parsed = State::parse(input, parser);
+        let node = &parsed.nodes[0];
+        let last = node.children.last().unwrap();
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert!(last.is(NodeId::ERROR));
+        assert_eq!(last.span.range, (input.len(), 0));
+    }
+}
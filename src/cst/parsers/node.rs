@@ -11,20 +11,49 @@ pub fn v_node(name: impl Into<Option<NodeId>>, f: impl Fn(&mut State)) -> impl P
 
 fn node_inner(name: NodeId, alias: Vec<NodeId>, f: impl Fn(&mut State)) -> impl Parser {
     move |state: &mut State| {
+        match state.options.max_depth {
+            Some(max) if name != NodeId::VIRTUAL && state.nodes.len() > max => {
+                return raise(StateError::MaxDepth(max), 0).parse(state);
+            }
+            _ => (),
+        }
         let n = Node {
             name,
             span: state.input.clone(),
             alias: alias.clone(),
             children: vec![],
         };
+        let trace = state.options.trace;
+        if trace {
+            trace_node(state, &n, true);
+        }
         state.nodes.push(n);
         f(state);
         let mut res = state.nodes.pop().expect("Node");
         res.recalc_span(state);
+        if trace {
+            trace_node(state, &res, false);
+        }
         res
     }
 }
 
+fn trace_node(state: &State, node: &Node, entering: bool) {
+    let name = node.alias.first().unwrap_or(&node.name).0;
+    let indent = (state.nodes.len() - 1) * 2;
+    if entering {
+        eprintln!(
+            "{:indent$}{} @ {}",
+            "",
+            name,
+            node.span.range.0,
+            indent = indent
+        );
+    } else {
+        eprintln!("{:indent$}{} = {:?}", "", name, node.span, indent = indent);
+    }
+}
+
 pub fn node(name: NodeId, f: impl Fn(&mut State)) -> impl Parser {
     node_inner(name, vec![], f)
 }
@@ -0,0 +1,299 @@
+use crate::*;
+use derive_more::Display;
+
+node_ids! {
+    pub Num:
+        Number,
+        Sign,
+        Radix,
+        Integer,
+        Fraction,
+        Exponent,
+        Digits
+}
+
+#[derive(Debug, Display, Clone)]
+enum NumberError {
+    #[display(fmt = "I expected {} digits", _0)]
+    ExpectedDigits(&'static str),
+
+    #[display(fmt = "I expected digits after `.`")]
+    FractionDigits,
+
+    #[display(fmt = "I expected digits in the exponent")]
+    ExponentDigits,
+
+    #[display(fmt = "I didn't expect leading zeros")]
+    LeadingZeros,
+
+    #[display(fmt = "I didn't expect `{}` in a number", _0)]
+    UnexpectedSuffix(String),
+}
+
+/// Which number syntax is accepted by `number`.
+#[derive(Debug, Clone, Copy)]
+pub struct NumberFormat {
+    /// Leading `-`.
+    pub minus: bool,
+    /// Leading `+`.
+    pub plus: bool,
+    /// `.5` part.
+    pub fraction: bool,
+    /// `e10`, `E-3` part.
+    pub exponent: bool,
+    /// `0x`, `0o` and `0b` prefixes.
+    pub radix: bool,
+    /// `_` between digits, like `1_000`.
+    pub separators: bool,
+    /// `007`.
+    pub leading_zeros: bool,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            minus: true,
+            plus: false,
+            fraction: true,
+            exponent: true,
+            radix: true,
+            separators: true,
+            leading_zeros: true,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Number as defined by RFC 8259.
+    pub fn json() -> Self {
+        Self {
+            minus: true,
+            plus: false,
+            fraction: true,
+            exponent: true,
+            radix: false,
+            separators: false,
+            leading_zeros: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Radix {
+    name: &'static str,
+    digit: fn(&str) -> bool,
+}
+
+const DECIMAL: Radix = Radix {
+    name: "decimal",
+    digit: is_digits,
+};
+const HEXADECIMAL: Radix = Radix {
+    name: "hexadecimal",
+    digit: is_hex,
+};
+const OCTAL: Radix = Radix {
+    name: "octal",
+    digit: is_octal,
+};
+const BINARY: Radix = Radix {
+    name: "binary",
+    digit: is_binary,
+};
+
+fn is_octal(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| ('0'..='7').contains(&c))
+}
+
+fn is_binary(s: &str) -> bool {
+    s == "0" || s == "1"
+}
+
+/// Digits in given radix, optionally with single `_` separators between them.
+/// Leading, trailing and doubled `_` are left for the suffix check.
+fn digits(radix: Radix, separators: bool) -> impl Parser<Span> {
+    move |state: &mut State| {
+        let mut len = 0;
+        let mut steps = state.cursor().map(|(_, step)| step).peekable();
+        while let Some(step) = steps.next() {
+            let separator = separators
+                && len > 0
+                && step == "_"
+                && steps.peek().map(|next| (radix.digit)(next)).unwrap_or_default();
+            if !separator && !(radix.digit)(step) {
+                break;
+            }
+            len += step.len();
+        }
+        state.input.chomp_chars(len)
+    }
+}
+
+fn sign(format: NumberFormat) -> impl Parser {
+    move |state: &mut State| match state.peek(1).as_ref() {
+        "-" if format.minus => recognize(Num::Sign, chomp(1)).parse(state),
+        "+" if format.plus => recognize(Num::Sign, chomp(1)).parse(state),
+        _ => none().parse(state),
+    }
+}
+
+fn radix(state: &mut State) -> Option<Radix> {
+    match state.peek(2).as_ref() {
+        "0x" | "0X" => Some(HEXADECIMAL),
+        "0o" | "0O" => Some(OCTAL),
+        "0b" | "0B" => Some(BINARY),
+        _ => None,
+    }
+}
+
+fn integer_part(format: NumberFormat) -> impl Parser {
+    move |state: &mut State| {
+        let digits = digits(DECIMAL, format.separators);
+        let checkpoint = state.checkpoint();
+        let span = digits.parse(state);
+        let leading_zero = span.as_ref().len() > 1 && span.as_ref().starts_with('0');
+        if format.leading_zeros || !leading_zero {
+            state.rewind(checkpoint);
+            return recognize1(Num::Integer, digits, NumberError::ExpectedDigits("decimal"))
+                .parse(state);
+        }
+        state.rewind(checkpoint);
+        raise(NumberError::LeadingZeros, span.len()).parse(state)
+    }
+}
+
+fn fraction(format: NumberFormat) -> impl Parser {
+    node(Num::Fraction, move |state| {
+        state.add(".");
+        state.add(recognize1(
+            Num::Digits,
+            digits(DECIMAL, format.separators),
+            NumberError::FractionDigits,
+        ));
+    })
+}
+
+fn exponent(format: NumberFormat) -> impl Parser {
+    node(Num::Exponent, move |state| {
+        state.add(token_ci("e"));
+        state.add(recognize(Num::Sign, "-".or("+")));
+        state.add(recognize1(
+            Num::Digits,
+            digits(DECIMAL, format.separators),
+            NumberError::ExponentDigits,
+        ));
+    })
+}
+
+/// Number literal like `-12.5e3` or `0xFF`, according to `format`.
+///
+/// `Number` node has `Sign`, `Radix`, `Integer`, `Fraction` and `Exponent` children.
+/// Fraction and exponent keep their digits in `Digits` node.
+pub fn number(format: NumberFormat) -> impl Parser {
+    node(Num::Number, move |state| {
+        state.add(sign(format));
+        match radix(state) {
+            Some(radix) if format.radix => {
+                state.add(recognize(Num::Radix, chomp(2)));
+                state.add(recognize1(
+                    Num::Integer,
+                    digits(radix, format.separators),
+                    NumberError::ExpectedDigits(radix.name),
+                ));
+            }
+            _ => {
+                state.add(integer_part(format));
+                if format.fraction && state.peek(1).as_ref() == "." {
+                    state.add(fraction(format));
+                }
+                if format.exponent {
+                    if let "e" | "E" = state.peek(1).as_ref() {
+                        state.add(exponent(format));
+                    }
+                }
+            }
+        }
+        if is_xid_continue(state.peek(1).as_ref()) {
+            let checkpoint = state.checkpoint();
+            let suffix = state.chomp_while(is_xid_continue);
+            state.rewind(checkpoint);
+            let problem = NumberError::UnexpectedSuffix(suffix.as_ref().to_string());
+            state.add(raise(problem, suffix.len()));
+        }
+    })
+}
+
+/// Integer literal without fraction and exponent, e.g. `-1_000` or `0b1010`.
+pub fn integer() -> impl Parser {
+    number(NumberFormat {
+        fraction: false,
+        exponent: false,
+        ..NumberFormat::default()
+    })
+}
+
+/// Decimal number with optional fraction and exponent, e.g. `-1.5e3`.
+pub fn float() -> impl Parser {
+    number(NumberFormat {
+        radix: false,
+        ..NumberFormat::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn children(parsed: &Parsed) -> Vec<(NodeId, String)> {
+        parsed.nodes[0]
+            .children
+            .iter()
+            .map(|n| (n.name, n.span.as_ref().to_string()))
+            .collect()
+    }
+
+    #[test_case(integer(), "42", &[(Num::Integer, "42")])]
+    #[test_case(integer(), "-1_000", &[(Num::Sign, "-"), (Num::Integer, "1_000")])]
+    #[test_case(integer(), "0xFF", &[(Num::Radix, "0x"), (Num::Integer, "FF")])]
+    #[test_case(integer(), "0b1010", &[(Num::Radix, "0b"), (Num::Integer, "1010")])]
+    #[test_case(float(), "1.5", &[(Num::Integer, "1"), (Num::Fraction, ".5")])]
+    #[test_case(float(), "-2.5E-3", &[(Num::Sign, "-"), (Num::Integer, "2"), (Num::Fraction, ".5"), (Num::Exponent, "E-3")])]
+    #[test_case(number(NumberFormat::json()), "0", &[(Num::Integer, "0")])]
+    fn structure(parser: impl Parser, input: &str, expected: &[(NodeId, &str)]) {
+        let parsed = State::parse(input, parser);
+        let expected = expected
+            .iter()
+            .map(|(name, span)| (*name, span.to_string()))
+            .collect::<Vec<_>>();
+
+        assert!(parsed.errors.is_empty());
+        assert!(parsed.rest.is_empty());
+        assert_eq!(children(&parsed), expected);
+    }
+
+    #[test_case(float(), "1.", "", "I expected digits after `.`")]
+    #[test_case(float(), "1e+", "", "I expected digits in the exponent")]
+    #[test_case(float(), "-", "", "I expected decimal digits")]
+    #[test_case(integer(), "0x", "", "I expected hexadecimal digits")]
+    #[test_case(integer(), "0b102", "2", "I didn't expect `2` in a number")]
+    #[test_case(integer(), "12px", "px", "I didn't expect `px` in a number")]
+    #[test_case(integer(), "1_000_", "_", "I didn't expect `_` in a number")]
+    #[test_case(integer(), "1__0", "__0", "I didn't expect `__0` in a number")]
+    #[test_case(integer(), "0x_F", "_F", "I expected hexadecimal digits")]
+    #[test_case(float(), "1.5_e3", "_e3", "I didn't expect `_e3` in a number")]
+    #[test_case(
+        number(NumberFormat::json()),
+        "007",
+        "007",
+        "I didn't expect leading zeros"
+    )]
+    #[test_case(number(NumberFormat::json()), "+1", "", "I expected decimal digits")]
+    fn problems(parser: impl Parser, input: &str, span: &str, expected: &str) {
+        let parsed = State::parse(input, parser);
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].span.as_ref(), span);
+        assert_eq!(parsed.errors[0].problem.to_string(), expected);
+    }
+}
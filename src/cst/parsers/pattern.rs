@@ -0,0 +1,55 @@
+use crate::*;
+use regex::Regex;
+
+/// Recognizes a match of `pattern` anchored at the current position.
+///
+/// Length is counted in whole steps of `Unit` like `chomp`,
+/// so a match ending inside a grapheme cluster is shortened to the previous cluster.
+///
+/// Pattern is compiled once, when the parser is created.
+/// Panics if `pattern` is not a valid regular expression.
+pub fn regex(pattern: &str) -> impl Parser<Span> {
+    let regex = Regex::new(&format!("^(?:{})", pattern)).expect("Invalid regex pattern");
+
+    move |state: &mut State| {
+        let end = regex
+            .find(state.input.as_ref())
+            .map(|found| found.end())
+            .unwrap_or_default();
+        let len = state
+            .cursor()
+            .take_while(|(offset, grapheme)| offset + grapheme.len() <= end)
+            .count();
+        state.chomp(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(r"\d{4}-\d{2}-\d{2}", "2020-02-29T10:00", "2020-02-29", "T10:00")]
+    #[test_case(r"\d+\.\d+\.\d+", "1.2.3-beta", "1.2.3", "-beta")]
+    #[test_case(r"\d+", "v1", "", "v1")]
+    #[test_case(r"a|ab", "abc", "a", "bc")]
+    #[test_case("e", "e\u{301}x", "", "e\u{301}x")]
+    #[test_case(r"e\u{301}", "e\u{301}x", "e\u{301}", "x")]
+    fn regex_recognizer(pattern: &str, input: &str, expected: &str, expected_rest: &str) {
+        let mut state: State = input.into();
+        let actual = regex(pattern).parse(&mut state);
+
+        assert_eq!(actual.as_ref(), expected);
+        assert_eq!(state.input.as_ref(), expected_rest);
+    }
+
+    #[test]
+    fn with_recognize1() {
+        let uuid = "[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}";
+        let parser = recognize1(NodeId("Uuid"), regex(uuid), "I expected UUID");
+
+        let parsed = State::parse("123e4567-e89b-12d3-a456-426614174000", parser);
+        assert!(parsed.errors.is_empty());
+        assert!(parsed.rest.is_empty());
+    }
+}
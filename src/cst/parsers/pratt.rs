@@ -1,63 +1,741 @@
 use crate::*;
+use derive_more::Display;
+use std::sync::Arc;
 
-/*
-pub fn pratt(
-    name: NodeId,
+node_ids! {
+    pub Op:
+        Binary,
+        Prefix,
+        Postfix,
+        Call,
+        Arguments,
+        Index,
+        Member,
+        Conditional
+}
+
+#[derive(Debug, Display, Clone)]
+enum PrattError {
+    #[display(fmt = "I didn't expect chained `{}`, add parentheses", _0)]
+    Chained(String),
+
+    #[display(fmt = "I expected `{}` or `{}`", _0, _1)]
+    Arguments(&'static str, &'static str),
+
+    #[display(fmt = "I expected `{}`", _0)]
+    Close(&'static str),
+
+    #[display(fmt = "I expected an operator like {}", _0)]
+    UnknownOperator(String),
+}
+
+/// How operators of the same binding power group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`.
+    Right,
+    /// `a < b < c` is reported as a problem.
+    NonAssoc,
+}
+
+/// What follows the operator parser.
+enum Shape {
+    Simple,
+    Call {
+        open: &'static str,
+        separator: &'static str,
+        close: &'static str,
+    },
+    Index {
+        close: &'static str,
+    },
+    Member {
+        field: Box<dyn Parser>,
+    },
+    Conditional {
+        separator: Box<dyn Parser>,
+    },
+    /// Fallback of `OperatorTable` for unknown infix operators.
+    Unknown,
+}
+
+/// Operator found by one of `Pratt` lookups.
+pub struct Operator {
+    bp: i32,
+    assoc: Assoc,
+    name: Option<NodeId>,
+    parser: Box<dyn Parser>,
+    shape: Shape,
+}
+
+impl Operator {
+    /// Left-associative operator recognized by `parser`.
+    /// Higher binding power binds tighter, it has to be greater than zero.
+    pub fn new(bp: i32, parser: impl Parser + 'static) -> Self {
+        Self {
+            bp,
+            assoc: Assoc::Left,
+            name: None,
+            parser: parser.boxed(),
+            shape: Shape::Simple,
+        }
+    }
+
+    fn shape(mut self, shape: Shape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Postfix call with arguments, like `f(a, b)`, built as `Op::Call` with `Op::Arguments`.
+    ///
+    /// Arguments are whole expressions. Unclosed list is recovered like in `delimited_list`.
+    pub fn call(bp: i32, open: &'static str, separator: &'static str, close: &'static str) -> Self {
+        Self::new(bp, open).shape(Shape::Call {
+            open,
+            separator,
+            close,
+        })
+    }
+
+    /// Postfix index, like `a[i]`, built as `Op::Index`.
+    pub fn index(bp: i32, open: &'static str, close: &'static str) -> Self {
+        Self::new(bp, open).shape(Shape::Index { close })
+    }
+
+    /// Postfix member access, like `a.b`, built as `Op::Member`.
+    pub fn member(bp: i32, dot: impl Parser + 'static, field: impl Parser + 'static) -> Self {
+        Self::new(bp, dot).shape(Shape::Member {
+            field: field.boxed(),
+        })
+    }
+
+    /// Right-associative infix `c ? x : y`, built as `Op::Conditional`.
+    ///
+    /// The middle expression may contain any operator, like in parentheses.
+    pub fn conditional(
+        bp: i32,
+        question: impl Parser + 'static,
+        separator: impl Parser + 'static,
+    ) -> Self {
+        Self::new(bp, question).right().shape(Shape::Conditional {
+            separator: separator.boxed(),
+        })
+    }
+
+    pub fn assoc(mut self, assoc: Assoc) -> Self {
+        self.assoc = assoc;
+        self
+    }
+
+    pub fn right(self) -> Self {
+        self.assoc(Assoc::Right)
+    }
+
+    pub fn non_assoc(self) -> Self {
+        self.assoc(Assoc::NonAssoc)
+    }
+
+    /// Name of the built node instead of the default one from `Op`.
+    pub fn node(mut self, name: NodeId) -> Self {
+        self.name = Some(name);
+        self
+    }
+}
+
+type Lookup = Arc<dyn Fn(&mut State) -> Option<Operator>>;
+
+/// Pratt parser for expressions made of `operand`s and operators.
+///
+/// Lookups peek at the input and return the operator found there, if any.
+/// Operator parsers are run only when the operator binds at given position.
+#[derive(Clone)]
+pub struct Pratt {
+    operand: Arc<dyn Parser>,
     alias: Vec<NodeId>,
-    next: impl Clone + Parser,
-    bp: impl Clone + for<'a> Fn(&'a str) -> Option<(i32, &'static str)>,
-) -> impl Parser {
-    infix(name, alias, 0, next, bp)
+    prefix: Option<Lookup>,
+    infix: Option<Lookup>,
+    postfix: Option<Lookup>,
+}
+
+impl Pratt {
+    pub fn new(operand: impl Parser + 'static) -> Self {
+        Self {
+            operand: operand.arc(),
+            alias: vec![],
+            prefix: None,
+            infix: None,
+            postfix: None,
+        }
+    }
+
+    /// Aliases of every node built by the parser.
+    pub fn alias(mut self, alias: Vec<NodeId>) -> Self {
+        self.alias = alias;
+        self
+    }
+
+    /// Operators before an operand, like `-a`. Associativity is ignored.
+    pub fn prefix(mut self, lookup: impl Fn(&mut State) -> Option<Operator> + 'static) -> Self {
+        self.prefix = Some(Arc::new(lookup));
+        self
+    }
+
+    /// Operators between operands, like `a + b`.
+    pub fn infix(mut self, lookup: impl Fn(&mut State) -> Option<Operator> + 'static) -> Self {
+        self.infix = Some(Arc::new(lookup));
+        self
+    }
+
+    /// Operators after an operand, like `a!`. Associativity is ignored.
+    pub fn postfix(mut self, lookup: impl Fn(&mut State) -> Option<Operator> + 'static) -> Self {
+        self.postfix = Some(Arc::new(lookup));
+        self
+    }
+
+    /// Uses operators from `table`, replacing prefix, infix and postfix lookups.
+    pub fn table(self, table: OperatorTable) -> Self {
+        let table = Arc::new(table);
+        let (prefix, infix, postfix) = (table.clone(), table.clone(), table);
+        self.prefix(move |state| prefix.lookup(state, true))
+            .infix(move |state| infix.find_infix(state))
+            .postfix(move |state| postfix.find_postfix(state))
+    }
+
+    fn lookup(lookup: &Option<Lookup>, state: &mut State, min_bp: i32) -> Option<Operator> {
+        lookup
+            .as_ref()
+            .and_then(|lookup| lookup(state))
+            .filter(|op| op.bp * 2 > min_bp)
+    }
+
+    fn operation(&self, state: &mut State, name: NodeId, f: impl FnOnce(&mut State)) -> Node {
+        state.nodes.push(Node {
+            name,
+            span: state.input.clone(),
+            alias: self.alias.clone(),
+            children: vec![],
+        });
+        f(state);
+        let mut node = state.nodes.pop().expect("Node");
+        node.recalc_span(state);
+        node
+    }
+
+    /// Binding powers are doubled, so right-associative operators can bind
+    /// operators of the same level without binding the lower ones.
+    ///
+    /// Erroneous operands stay in the tree as `ERROR` nodes and parsing goes on,
+    /// every recognized operator ends the panic mode, so later problems are reported too.
+    /// Unknown operators of `OperatorTable` found in the panic mode are added to the error.
+    fn expression(&self, state: &mut State, min_bp: i32) -> Node {
+        let mut left = match Self::lookup(&self.prefix, state, 0) {
+            Some(op) => self.operation(state, op.name.unwrap_or(Op::Prefix), |state| {
+                state.add(recover(|state: &mut State| op.parser.parse(state)));
+                state.add(|state: &mut State| self.expression(state, op.bp * 2));
+            }),
+            None => self.operand.parse(state),
+        };
+
+        let mut previous: Option<(i32, Assoc)> = None;
+        loop {
+            if let Some(op) = Self::lookup(&self.postfix, state, min_bp) {
+                let start = state.input.clone();
+                left = self.postfix_operation(state, left, op);
+                if state.input == start {
+                    return left;
+                }
+                previous = None;
+                continue;
+            }
+
+            let op = match Self::lookup(&self.infix, state, min_bp) {
+                Some(op) => op,
+                None => return left,
+            };
+            if let (Shape::Unknown, true) = (&op.shape, state.panic) {
+                // Junk right after an erroneous operand only extends its error.
+                let start = state.input.clone();
+                left = self.operation(state, NodeId::VIRTUAL, |state| {
+                    state.add_node(left);
+                    state.add(|state: &mut State| op.parser.parse(state));
+                });
+                if state.input == start {
+                    return left;
+                }
+                continue;
+            }
+            let chained = op.assoc == Assoc::NonAssoc && previous == Some((op.bp, op.assoc));
+            previous = Some((op.bp, op.assoc));
+            let start = state.input.clone();
+            left = self.infix_operation(state, left, op, chained);
+            if state.input == start {
+                return left;
+            }
+        }
+    }
+
+    fn postfix_operation(&self, state: &mut State, left: Node, op: Operator) -> Node {
+        let name = match op.shape {
+            Shape::Call { .. } => Op::Call,
+            Shape::Index { .. } => Op::Index,
+            Shape::Member { .. } => Op::Member,
+            _ => Op::Postfix,
+        };
+        self.operation(state, op.name.unwrap_or(name), |state| {
+            state.add_node(left);
+            let item = |state: &mut State| self.expression(state, 0);
+            match op.shape {
+                Shape::Call {
+                    open,
+                    separator,
+                    close,
+                } => state.add(node(Op::Arguments, |state| {
+                    let problem = PrattError::Arguments(separator, close);
+                    state.add(delimited_list(open, item, separator, close, problem));
+                })),
+                Shape::Index { close } => {
+                    state.add(recover(|state: &mut State| op.parser.parse(state)));
+                    state.add(item);
+                    state.add(recover(close.label(PrattError::Close(close))));
+                }
+                Shape::Member { ref field } => {
+                    state.add(recover(|state: &mut State| op.parser.parse(state)));
+                    state.add(|state: &mut State| field.parse(state));
+                }
+                _ => state.add(recover(|state: &mut State| op.parser.parse(state))),
+            }
+        })
+    }
+
+    fn infix_operation(&self, state: &mut State, left: Node, op: Operator, chained: bool) -> Node {
+        let name = match op.shape {
+            Shape::Conditional { .. } => Op::Conditional,
+            _ => Op::Binary,
+        };
+        let rbp = match op.assoc {
+            Assoc::Right => op.bp * 2 - 1,
+            _ => op.bp * 2,
+        };
+        self.operation(state, op.name.unwrap_or(name), |state| {
+            state.add_node(left);
+            state.add(recover(|state: &mut State| {
+                let node = op.parser.parse(state);
+                if chained {
+                    let problem = PrattError::Chained(node.span.as_ref().to_string());
+                    report(state, problem, node.span.clone());
+                }
+                node
+            }));
+            if let Shape::Conditional { ref separator } = op.shape {
+                state.add(|state: &mut State| self.expression(state, 0));
+                state.add(recover(|state: &mut State| separator.parse(state)));
+            }
+            state.add(|state: &mut State| self.expression(state, rbp));
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fixity {
+    Prefix,
+    Infix(Assoc),
+    Postfix,
+}
+
+#[derive(Debug, Clone)]
+struct Level {
+    fixity: Fixity,
+    operators: Vec<&'static str>,
+    name: Option<NodeId>,
+}
+
+/// Operators grouped in precedence levels, from the loosest to the tightest, see `Pratt::table`.
+///
+/// E.g. `.level(Left, vec!["+", "-"]).level(Left, vec!["*", "/"]).level(Right, vec!["^"])`.
+/// The longest operator matching the input wins, so `**` can be used next to `*`.
+/// Unknown infix operators are reported, unless the input ends or continues with one of `closing`.
+#[derive(Debug, Clone)]
+pub struct OperatorTable {
+    levels: Vec<Level>,
+    closing: Vec<&'static str>,
 }
-*/
+
+impl Default for OperatorTable {
+    fn default() -> Self {
+        Self {
+            levels: vec![],
+            closing: vec![")", "]", "}", ",", ";", ":"],
+        }
+    }
+}
+
+impl OperatorTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(mut self, fixity: Fixity, operators: Vec<&'static str>) -> Self {
+        self.levels.push(Level {
+            fixity,
+            operators,
+            name: None,
+        });
+        self
+    }
+
+    /// Infix operators binding tighter than all previous levels.
+    pub fn level(self, assoc: Assoc, operators: Vec<&'static str>) -> Self {
+        self.push(Fixity::Infix(assoc), operators)
+    }
+
+    /// Prefix operators binding tighter than all previous levels.
+    pub fn prefix(self, operators: Vec<&'static str>) -> Self {
+        self.push(Fixity::Prefix, operators)
+    }
+
+    /// Postfix operators binding tighter than all previous levels.
+    pub fn postfix(self, operators: Vec<&'static str>) -> Self {
+        self.push(Fixity::Postfix, operators)
+    }
+
+    /// Name of nodes built by operators of the last level, see `Operator::node`.
+    pub fn node(mut self, name: NodeId) -> Self {
+        if let Some(level) = self.levels.last_mut() {
+            level.name = Some(name);
+        }
+        self
+    }
+
+    /// Tokens which may follow an expression, `)`, `]`, `}`, `,`, `;` and `:` by default.
+    pub fn closing(mut self, closing: Vec<&'static str>) -> Self {
+        self.closing = closing;
+        self
+    }
+
+    /// The longest operator at the beginning of the input.
+    fn lookup(&self, state: &State, prefix: bool) -> Option<Operator> {
+        let input = state.input.as_ref();
+        let found = self
+            .levels
+            .iter()
+            .enumerate()
+            .filter(|(_, level)| match level.fixity {
+                Fixity::Prefix => prefix,
+                _ => !prefix,
+            })
+            .flat_map(|(index, level)| level.operators.iter().map(move |op| (index, level, op)))
+            .filter(|(_, _, op)| input.starts_with(*op))
+            .max_by_key(|(_, _, op)| op.len());
+
+        let (index, level, op) = found?;
+        let mut operator = Operator::new(index as i32 + 1, token(op));
+        operator.name = level.name;
+        if let Fixity::Infix(assoc) = level.fixity {
+            operator.assoc = assoc;
+        }
+        Some(operator)
+    }
+
+    fn find_infix(&self, state: &State) -> Option<Operator> {
+        match self.lookup(state, false) {
+            Some(operator) if self.is_postfix(&operator) => None,
+            Some(operator) => Some(operator),
+            None => self.unknown(state),
+        }
+    }
+
+    fn find_postfix(&self, state: &State) -> Option<Operator> {
+        self.lookup(state, false)
+            .filter(|operator| self.is_postfix(operator))
+    }
+
+    fn is_postfix(&self, operator: &Operator) -> bool {
+        self.levels[operator.bp as usize - 1].fixity == Fixity::Postfix
+    }
+
+    fn unknown(&self, state: &State) -> Option<Operator> {
+        let input = state.input.as_ref();
+        if input.is_empty() || self.closing.iter().any(|close| input.starts_with(close)) {
+            return None;
+        }
+        let expected = self
+            .levels
+            .iter()
+            .filter(|level| level.fixity != Fixity::Prefix)
+            .flat_map(|level| level.operators.iter())
+            .map(|op| format!("`{}`", op))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let problem = PrattError::UnknownOperator(expected);
+        let bp = self.levels.len() as i32 + 1;
+        let operator = Operator::new(bp, move |state: &mut State| {
+            let len = state.peek(1).len();
+            raise(problem.clone(), len).parse(state)
+        });
+        Some(operator.shape(Shape::Unknown))
+    }
+}
+
+impl Parser for Pratt {
+    fn parse(&self, state: &mut State) -> Node {
+        self.expression(state, 0)
+    }
+}
+
+/// Left-associative binary operators found by `bp`, see `Pratt` for other kinds.
 pub fn pratt(
     name: NodeId,
     alias: Vec<NodeId>,
-    next: impl Clone + Parser,
-    bp: impl Clone + Fn(&mut State) -> Option<(i32, Box<dyn Parser>)>,
+    next: impl Parser + 'static,
+    bp: impl Fn(&mut State) -> Option<(i32, Box<dyn Parser>)> + 'static,
 ) -> impl Parser {
     infix(name, alias, 0, next, bp)
 }
 
+/// Like `pratt`, but only operators binding tighter than `rbp` are parsed.
 pub fn infix(
     name: NodeId,
     alias: Vec<NodeId>,
     rbp: i32,
-    next: impl Clone + Parser,
-    bp: impl Clone + Fn(&mut State) -> Option<(i32, Box<dyn Parser>)>,
+    next: impl Parser + 'static,
+    bp: impl Fn(&mut State) -> Option<(i32, Box<dyn Parser>)> + 'static,
 ) -> impl Parser {
-    move |state: &mut State| {
-        let mut left = next.parse(state);
-        if left.is(NodeId::ERROR) || left.has(NodeId::ERROR) {
-            return left;
-        }
-        loop {
-            let span = state.input.clone();
-            let (op_bp, op_token) = match bp(state) {
-                Some(op) if op.0 > rbp => op,
-                _ => return left,
-            };
+    let pratt = Pratt::new(next).alias(alias).infix(move |state| {
+        bp(state).map(|(bp, parser)| {
+            Operator::new(bp, move |state: &mut State| parser.parse(state)).node(name)
+        })
+    });
+    move |state: &mut State| pratt.expression(state, rbp * 2)
+}
 
-            let res = Node {
-                name,
-                span,
-                alias: alias.clone(),
-                children: vec![],
-            };
-            state.nodes.push(res);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
 
-            state.add_node(left);
-            state.add(|state: &mut State| op_token.parse(state));
-            state.add(infix(
-                name,
-                alias.clone(),
-                op_bp - 1,
-                next.clone(),
-                bp.clone(),
-            ));
-
-            left = state.nodes.pop().expect("Node");
-            left.recalc_span(state);
+    fn ws() -> Arc<dyn Parser> {
+        recognize(NodeId("WS"), chomp_while(is_ws)).arc()
+    }
+
+    fn atom(state: &mut State) -> Node {
+        v_node(None, |state| match state.peek(1).as_ref() {
+            "(" => {
+                state.add("(");
+                state.add(expression());
+                state.add(recover(")"));
+            }
+            _ => state.add(recognize1(
+                NodeId("Atom"),
+                chomp_while(is_xid_continue),
+                "I expected an operand",
+            )),
+        })
+        .parse(state)
+    }
+
+    fn expression() -> impl Parser {
+        let pratt = Pratt::new(atom)
+            .prefix(|state| match state.peek(1).as_ref() {
+                "-" => Some(Operator::new(5, "-")),
+                _ => None,
+            })
+            .infix(|state| match state.peek(1).as_ref() {
+                "?" => Some(Operator::conditional(1, "?", ":")),
+                "<" => Some(Operator::new(2, "<").non_assoc()),
+                "+" => Some(Operator::new(3, "+")),
+                "-" => Some(Operator::new(3, "-")),
+                "*" => Some(Operator::new(4, "*")),
+                "^" => Some(Operator::new(6, "^").right()),
+                _ => None,
+            })
+            .postfix(|state| match state.peek(1).as_ref() {
+                "!" => Some(Operator::new(7, "!")),
+                ";" => Some(Operator::new(1, ";")),
+                "(" => Some(Operator::call(8, "(", ",", ")")),
+                "[" => Some(Operator::index(8, "[", "]")),
+                "." => Some(Operator::member(8, ".", atom)),
+                _ => None,
+            });
+        with_extra(ws(), pratt)
+    }
+
+    fn table() -> impl Parser {
+        use Assoc::*;
+        let table = OperatorTable::new()
+            .level(NonAssoc, vec!["<", "<="])
+            .level(Left, vec!["+", "-"])
+            .level(Left, vec!["*", "/"])
+            .prefix(vec!["-"])
+            .node(NodeId("Negation"))
+            .level(Right, vec!["^", "**"])
+            .postfix(vec!["!"]);
+        with_extra(ws(), Pratt::new(atom).table(table))
+    }
+
+    /// Operations in parentheses, e.g. `(a + (b * c))`. Empty errors are skipped.
+    fn sexp(node: &Node) -> String {
+        let children = node
+            .children
+            .iter()
+            .filter(|child| !child.is(NodeId::EXTRA) && !child.span.is_empty())
+            .collect::<Vec<_>>();
+        if children.is_empty() || node.is(NodeId("Atom")) {
+            return node.span.as_ref().to_string();
+        }
+        let inner = children.into_iter().map(sexp).collect::<Vec<_>>().join(" ");
+        if node.is(NodeId::ROOT) {
+            return inner;
         }
+        format!("({})", inner)
+    }
+
+    #[test_case("a", "a")]
+    #[test_case("a + b * c", "(a + (b * c))")]
+    #[test_case("a - b - c", "((a - b) - c)")]
+    #[test_case("a ^ b ^ c", "(a ^ (b ^ c))")]
+    #[test_case("-a * b", "((- a) * b)")]
+    #[test_case("-a ^ b", "(- (a ^ b))")]
+    #[test_case("- -a", "(- (- a))")]
+    #[test_case("-a!", "(- (a !))")]
+    #[test_case("a + b;", "((a + b) ;)")]
+    #[test_case("a! ^ b!", "((a !) ^ (b !))")]
+    #[test_case("a < b + c", "(a < (b + c))")]
+    #[test_case("f(a, b + c)", "(f (( a , (b + c) )))")]
+    #[test_case("f()", "(f (( )))")]
+    #[test_case("f(a)(b)", "((f (( a ))) (( b )))")]
+    #[test_case("a.b.c", "((a . b) . c)")]
+    #[test_case("a[i + 1]", "(a [ (i + 1) ])")]
+    #[test_case("-f(x)!", "(- ((f (( x ))) !))")]
+    #[test_case("a.b(c)[d]", "(((a . b) (( c ))) [ d ])")]
+    #[test_case("c ? x : y ? z : w", "(c ? x : (y ? z : w))")]
+    #[test_case("c ? a ? b : d : e", "(c ? (a ? b : d) : e)")]
+    #[test_case("a < b ? x + 1 : y", "((a < b) ? (x + 1) : y)")]
+    fn precedence(input: &str, expected: &str) {
+        let parsed = State::parse(input, expression());
+
+        assert!(parsed.errors.is_empty());
+        assert!(parsed.rest.is_empty());
+        assert_eq!(sexp(parsed.root()), expected);
+    }
+
+    #[test_case("a < b < c", "<", "I didn't expect chained `<`, add parentheses")]
+    #[test_case("a < b + c < d", "<", "I didn't expect chained `<`, add parentheses")]
+    #[test_case("a + ", "", "I expected an operand")]
+    #[test_case("f(a, b", "", "I expected `,` or `)`")]
+    #[test_case("f(a b)", "", "I expected `,` or `)`")]
+    #[test_case("a[i", "", "I expected `]`")]
+    #[test_case("c ? x", "", "I expected `:`")]
+    fn problems(input: &str, span: &str, expected: &str) {
+        let parsed = State::parse(input, expression());
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].span.as_ref(), span);
+        assert_eq!(parsed.errors[0].problem.to_string(), expected);
+    }
+
+    #[test]
+    fn chained_is_still_parsed() {
+        let parsed = State::parse("a < b < c", expression());
+
+        assert_eq!(sexp(parsed.root()), "((a < b) < c)");
+        assert!(parsed.rest.is_empty());
+    }
+
+    #[test_case("a + b * c ^ d ^ e", "(a + (b * (c ^ (d ^ e))))")]
+    #[test_case("a ** b * c", "((a ** b) * c)")]
+    #[test_case("-a ^ b * c", "((- (a ^ b)) * c)")]
+    #[test_case("a <= b + c!", "(a <= (b + (c !)))")]
+    #[test_case("a - -b", "(a - (- b))")]
+    fn table_precedence(input: &str, expected: &str) {
+        let parsed = State::parse(input, table());
+
+        assert!(parsed.errors.is_empty());
+        assert!(parsed.rest.is_empty());
+        assert_eq!(sexp(parsed.root()), expected);
+    }
+
+    #[test_case(
+        "a % b",
+        "%",
+        "I expected an operator like `<`, `<=`, `+`, `-`, `*`, `/`, `^`, `**`, `!`"
+    )]
+    #[test_case(
+        "a \u{a7} b",
+        "\u{a7}",
+        "I expected an operator like `<`, `<=`, `+`, `-`, `*`, `/`, `^`, `**`, `!`"
+    )]
+    #[test_case("a < b <= c", "<=", "I didn't expect chained `<=`, add parentheses")]
+    fn table_problems(input: &str, span: &str, expected: &str) {
+        let parsed = State::parse(input, table());
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].span.as_ref(), span);
+        assert_eq!(parsed.errors[0].problem.to_string(), expected);
+    }
+
+    #[test]
+    fn table_adds_junk_to_bad_operand() {
+        let parsed = State::parse("a + %% * b", table());
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].span.as_ref(), "%%");
+        assert_eq!(sexp(parsed.root()), "(a + (%% * b))");
+        assert!(parsed.rest.is_empty());
+    }
+
+    #[test]
+    fn table_stops_at_closing() {
+        let parsed = State::parse("-a + b)", table());
+
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.rest.as_ref(), ")");
+        assert!(parsed.nodes[0].children[0].is(NodeId("Negation")));
+    }
+
+    #[test_case("a * * b + c", 1, "(((a *) * b) + c)")]
+    #[test_case("a * * b + + c", 2, "((((a *) * b) +) + c)")]
+    #[test_case("-  + b", 1, "((-) + b)")]
+    #[test_case("f(, b) * c", 1, "((f (( , b ))) * c)")]
+    fn continues_after_bad_operand(input: &str, errors: usize, expected: &str) {
+        let parsed = State::parse(input, expression());
+
+        assert_eq!(parsed.errors.len(), errors);
+        assert_eq!(sexp(parsed.root()), expected);
+        assert!(parsed.rest.is_empty());
+    }
+
+    #[test]
+    fn unclosed_arguments_recover() {
+        let parsed = State::parse("f(a b) + c", expression());
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(sexp(parsed.root()), "((f (( a b ))) + c)");
+        assert!(parsed.rest.is_empty());
+    }
+
+    #[test_case("f(x)", Op::Call)]
+    #[test_case("a[i]", Op::Index)]
+    #[test_case("a.b", Op::Member)]
+    #[test_case("c ? x : y", Op::Conditional)]
+    fn left_operand_is_first(input: &str, name: NodeId) {
+        let parsed = State::parse(input, expression());
+        let node = &parsed.nodes[0];
+
+        assert!(node.is(name));
+        assert_eq!(
+            node.children[0].span.as_ref().chars().next(),
+            input.chars().next()
+        );
+    }
+
+    #[test]
+    fn nodes() {
+        let parsed = State::parse("-a!", expression());
+        let prefix = &parsed.nodes[0];
+
+        assert!(prefix.is(Op::Prefix));
+        assert!(prefix.children[1].is(Op::Postfix));
     }
 }
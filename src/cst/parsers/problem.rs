@@ -10,6 +10,47 @@ pub fn recover(parser: impl Parser) -> impl Parser {
     }
 }
 
+/// Reports `problem` at any `span` without producing `ERROR` node,
+/// e.g. to point at the opening delimiter of an unterminated literal.
+/// Returns index of the recorded error, `None` once `max_errors` is reached.
+pub(crate) fn report(
+    state: &mut State,
+    problem: impl Problem + 'static,
+    span: Span,
+) -> Option<usize> {
+    let problem = Box::new(problem) as Box<dyn Problem + 'static>;
+    let context = state
+        .nodes
+        .iter()
+        .flat_map(|node| node.all_names_with_span())
+        .filter(|(name, _)| !NodeId::NO_CONTEXT.contains(name))
+        .map(|(name, span)| ParseErrorContext::new(name, span))
+        .collect();
+    let reached_max = state
+        .options
+        .max_errors
+        .map(|max| state.errors.len() >= max)
+        .unwrap_or_default();
+    if reached_max {
+        return None;
+    }
+    state.errors.push(ParseError::new(problem, span, context));
+    Some(state.errors.len() - 1)
+}
+
+/// Raises `failure` of a text parser like `raise(problem, 0)`,
+/// in the context it had when the text parser failed.
+pub(crate) fn raise_failure(state: &mut State, failure: Failure) -> Node {
+    let depth = state.nodes.len();
+    for name in failure.context.iter().rev() {
+        let node = Node::root(state.input.clone()).renamed(*name);
+        state.nodes.push(node);
+    }
+    let node = raise(failure.problem, 0).parse(state);
+    state.nodes.truncate(depth);
+    node
+}
+
 pub fn raise(problem: impl Problem + Clone + 'static, len: usize) -> impl Parser {
     move |state: &mut State| {
         let panic = state.panic;
@@ -18,23 +59,13 @@ pub fn raise(problem: impl Problem + Clone + 'static, len: usize) -> impl Parser
         match state.last_error() {
             Some(err) if panic => {
                 err.span.range.1 += len;
-                if let Some(error) = state.errors.last_mut() {
+                if let Some(error) = state.panic_error.and_then(|i| state.errors.get_mut(i)) {
                     error.span.range.1 += len;
                 }
                 none().parse(state)
             }
             _ if !panic => {
-                let problem = Box::new(problem.clone()) as Box<dyn Problem + 'static>;
-                let context = state
-                    .nodes
-                    .iter()
-                    .flat_map(|node| node.all_names_with_span())
-                    .filter(|(name, _)| !NodeId::NO_CONTEXT.contains(name))
-                    .map(|(name, span)| ParseErrorContext::new(name, span))
-                    .collect();
-                state
-                    .errors
-                    .push(ParseError::new(problem, span.clone(), context));
+                state.panic_error = report(state, problem.clone(), span.clone());
                 state.panic = true;
                 Node::error(span)
             }
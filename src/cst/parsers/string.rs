@@ -0,0 +1,407 @@
+use crate::*;
+use derive_more::Display;
+
+node_ids! {
+    pub Str:
+        String,
+        Text,
+        Escape
+}
+
+#[derive(Debug, Display, Clone)]
+enum StringError {
+    #[display(fmt = "I expected closing `{}`", _0)]
+    Unterminated(&'static str),
+
+    #[display(fmt = "I didn't expect escape sequence `{}`", _0)]
+    InvalidEscape(String),
+
+    #[display(fmt = "I expected {} hex digits in `{}`", _0, _1)]
+    ExpectedHexDigits(usize, String),
+
+    #[display(fmt = "I expected 1 to 6 hex digits between `{{` and `}}` in `{}`", _0)]
+    ExpectedBracedHex(String),
+
+    #[display(fmt = "I didn't expect `{}`, it is not a valid code point", _0)]
+    InvalidCodePoint(String),
+
+    #[display(fmt = "I didn't expect unpaired surrogate `{}`", _0)]
+    UnpairedSurrogate(String),
+}
+
+/// Form of `\u` escape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeEscape {
+    None,
+    /// `\u00e9`, with UTF-16 surrogate pairs like in JSON.
+    Hex4,
+    /// `\u00e9` and `\U000000e9` like in C.
+    Hex4And8,
+    /// `\u{e9}` like in Rust.
+    Braced,
+}
+
+/// Which string syntax is accepted by `string_literal`.
+#[derive(Debug, Clone, Copy)]
+pub struct StringFormat {
+    pub quote: &'static str,
+    /// Single character escapes, like `('n', '\n')` for `\n`.
+    pub escapes: &'static [(char, char)],
+    pub unicode: UnicodeEscape,
+    /// Highest value allowed in `\xHH` escape. `None` disables it.
+    pub hex: Option<u32>,
+    /// `\0` to `\377` escapes.
+    pub octal: bool,
+    /// Raw line endings inside the string.
+    pub multiline: bool,
+    /// `\` at the end of line skips the line ending and leading whitespace of the next line.
+    pub line_continuation: bool,
+}
+
+impl StringFormat {
+    pub fn json() -> Self {
+        Self {
+            quote: "\"",
+            escapes: &[
+                ('"', '"'),
+                ('\\', '\\'),
+                ('/', '/'),
+                ('b', '\u{8}'),
+                ('f', '\u{c}'),
+                ('n', '\n'),
+                ('r', '\r'),
+                ('t', '\t'),
+            ],
+            unicode: UnicodeEscape::Hex4,
+            hex: None,
+            octal: false,
+            multiline: false,
+            line_continuation: false,
+        }
+    }
+
+    pub fn rust() -> Self {
+        Self {
+            quote: "\"",
+            escapes: &[
+                ('"', '"'),
+                ('\'', '\''),
+                ('\\', '\\'),
+                ('0', '\0'),
+                ('n', '\n'),
+                ('r', '\r'),
+                ('t', '\t'),
+            ],
+            unicode: UnicodeEscape::Braced,
+            hex: Some(0x7F),
+            octal: false,
+            multiline: true,
+            line_continuation: true,
+        }
+    }
+
+    pub fn c() -> Self {
+        Self {
+            quote: "\"",
+            escapes: &[
+                ('"', '"'),
+                ('\'', '\''),
+                ('?', '?'),
+                ('\\', '\\'),
+                ('a', '\u{7}'),
+                ('b', '\u{8}'),
+                ('f', '\u{c}'),
+                ('n', '\n'),
+                ('r', '\r'),
+                ('t', '\t'),
+                ('v', '\u{b}'),
+            ],
+            unicode: UnicodeEscape::Hex4And8,
+            hex: Some(0xFF),
+            octal: true,
+            multiline: false,
+            line_continuation: false,
+        }
+    }
+}
+
+fn hex_prefix(s: &str, max: usize) -> &str {
+    let len = s
+        .chars()
+        .take(max)
+        .take_while(|c| c.is_ascii_hexdigit())
+        .count();
+    &s[..len]
+}
+
+fn is_surrogate(value: u32) -> bool {
+    (0xD800..=0xDFFF).contains(&value)
+}
+
+fn code_point(value: u32, text: &str) -> Result<Option<char>, StringError> {
+    std::char::from_u32(value)
+        .map(Some)
+        .ok_or_else(|| StringError::InvalidCodePoint(text.to_string()))
+}
+
+/// `\u` followed by exactly `digits` hex digits.
+fn fixed_hex(s: &str, digits: usize) -> (usize, Result<Option<char>, StringError>) {
+    let hex = hex_prefix(&s[2..], digits);
+    let len = 2 + hex.len();
+    if hex.len() < digits {
+        return (
+            len,
+            Err(StringError::ExpectedHexDigits(digits, s[..len].into())),
+        );
+    }
+    let value = u32::from_str_radix(hex, 16).unwrap_or_default();
+    if digits == 4 && is_surrogate(value) {
+        return surrogate_pair(s, value);
+    }
+    (len, code_point(value, &s[..len]))
+}
+
+fn surrogate_pair(s: &str, high: u32) -> (usize, Result<Option<char>, StringError>) {
+    let unpaired = (6, Err(StringError::UnpairedSurrogate(s[..6].into())));
+    if high >= 0xDC00 || !s[6..].starts_with("\\u") {
+        return unpaired;
+    }
+    let hex = hex_prefix(&s[8..], 4);
+    let low = u32::from_str_radix(hex, 16).unwrap_or_default();
+    if hex.len() < 4 || !(0xDC00..=0xDFFF).contains(&low) {
+        return unpaired;
+    }
+    let value = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+    (12, code_point(value, &s[..12]))
+}
+
+fn braced_hex(s: &str) -> (usize, Result<Option<char>, StringError>) {
+    let rest = &s[2..];
+    if !rest.starts_with('{') {
+        return (2, Err(StringError::ExpectedBracedHex(s[..2].into())));
+    }
+    let hex = hex_prefix(&rest[1..], 7);
+    let len = 3 + hex.len();
+    if hex.is_empty() || hex.len() > 6 || !rest[1 + hex.len()..].starts_with('}') {
+        return (len, Err(StringError::ExpectedBracedHex(s[..len].into())));
+    }
+    let len = len + 1;
+    let value = u32::from_str_radix(hex, 16).unwrap_or_default();
+    (len, code_point(value, &s[..len]))
+}
+
+fn octal(s: &str) -> (usize, Result<Option<char>, StringError>) {
+    let digits = s[1..]
+        .chars()
+        .take(3)
+        .take_while(|c| ('0'..='7').contains(c))
+        .count();
+    let len = 1 + digits;
+    let value = u32::from_str_radix(&s[1..len], 8).unwrap_or_default();
+    if value > 0o377 {
+        return (len, Err(StringError::InvalidCodePoint(s[..len].into())));
+    }
+    (len, code_point(value, &s[..len]))
+}
+
+/// Decodes escape sequence at the beginning of `s`.
+/// Returns its length in bytes and the decoded character, if any.
+fn decode_escape(format: &StringFormat, s: &str) -> (usize, Result<Option<char>, StringError>) {
+    let c = match s[1..].chars().next() {
+        Some(c) => c,
+        None => return (1, Err(StringError::InvalidEscape(s.into()))),
+    };
+    if let Some((_, decoded)) = format.escapes.iter().find(|(e, _)| *e == c) {
+        return (1 + c.len_utf8(), Ok(Some(*decoded)));
+    }
+    match c {
+        '\r' | '\n' if format.line_continuation => {
+            let rest = &s[1..];
+            let len = rest.len() - rest.trim_start().len();
+            (1 + len, Ok(None))
+        }
+        'u' if format.unicode == UnicodeEscape::Braced => braced_hex(s),
+        'u' if format.unicode != UnicodeEscape::None => fixed_hex(s, 4),
+        'U' if format.unicode == UnicodeEscape::Hex4And8 => fixed_hex(s, 8),
+        'x' if format.hex.is_some() => {
+            let hex = hex_prefix(&s[2..], 2);
+            let len = 2 + hex.len();
+            let value = u32::from_str_radix(hex, 16).unwrap_or_default();
+            match format.hex {
+                _ if hex.len() < 2 => {
+                    (len, Err(StringError::ExpectedHexDigits(2, s[..len].into())))
+                }
+                Some(max) if value > max => {
+                    (len, Err(StringError::InvalidCodePoint(s[..len].into())))
+                }
+                _ => (len, code_point(value, &s[..len])),
+            }
+        }
+        '0'..='7' if format.octal => octal(s),
+        c => {
+            let len = 1 + c.len_utf8();
+            (len, Err(StringError::InvalidEscape(s[..len].into())))
+        }
+    }
+}
+
+fn escape(format: StringFormat) -> impl Parser {
+    move |state: &mut State| {
+        let (len, decoded) = decode_escape(&format, state.input.as_ref());
+        match decoded {
+            Ok(_) => Node {
+                name: Str::Escape,
+                span: state.input.chomp_chars(len),
+                children: vec![],
+                alias: vec![],
+            },
+            Err(problem) => {
+                let node = raise(problem, len).parse(state);
+                // Escape problems are local, the rest of the string is still fine.
+                state.panic = false;
+                node
+            }
+        }
+    }
+}
+
+fn text(format: StringFormat) -> impl Parser {
+    recognize(
+        Str::Text,
+        chomp_until(move |c: &str| {
+            c == format.quote || c == "\\" || (!format.multiline && is_line_ending(c))
+        }),
+    )
+}
+
+/// String literal like `"foo\n"`, according to `format`.
+///
+/// `String` node has `Text` and `Escape` children between quote tokens.
+/// Use `unescape` to get the decoded value.
+pub fn string_literal(format: StringFormat) -> impl Parser {
+    no_extra(node(Str::String, move |state| {
+        state.add(recover(format.quote));
+        loop {
+            match state.peek(1).as_ref() {
+                "\\" => state.add(escape(format)),
+                q if q == format.quote => {
+                    state.add(format.quote);
+                    break;
+                }
+                c if c.is_empty() || (!format.multiline && is_line_ending(c)) => {
+                    state.add(raise(StringError::Unterminated(format.quote), 0));
+                    break;
+                }
+                _ => state.add(text(format)),
+            }
+        }
+    }))
+}
+
+/// Decodes escape sequences in `text`, which is the content of a string literal without quotes.
+/// Invalid escape sequences are kept as they are.
+pub fn unescape(format: &StringFormat, text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('\\') {
+        output.push_str(&rest[..index]);
+        rest = &rest[index..];
+        let (len, decoded) = decode_escape(format, rest);
+        match decoded {
+            Ok(Some(c)) => output.push(c),
+            Ok(None) => (),
+            Err(_) => output.push_str(&rest[..len]),
+        }
+        rest = &rest[len..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(StringFormat::json(), r#""a\"b""#, &[(Str::Text, "a"), (Str::Escape, r#"\""#), (Str::Text, "b")])]
+    #[test_case(StringFormat::json(), r#""\uD83D\uDE00""#, &[(Str::Escape, r#"\uD83D\uDE00"#)])]
+    #[test_case(StringFormat::rust(), r#""\u{1F600}!""#, &[(Str::Escape, r#"\u{1F600}"#), (Str::Text, "!")])]
+    #[test_case(StringFormat::c(), r#""\101\x41""#, &[(Str::Escape, r#"\101"#), (Str::Escape, r#"\x41"#)])]
+    #[test_case(StringFormat::json(), r#""""#, &[])]
+    fn segments(format: StringFormat, input: &str, expected: &[(NodeId, &str)]) {
+        let parsed = State::parse(input, string_literal(format));
+        let actual = parsed.nodes[0]
+            .children
+            .iter()
+            .filter(|n| !n.is(NodeId::TOKEN))
+            .map(|n| (n.name, n.span.as_ref()))
+            .collect::<Vec<_>>();
+
+        assert!(parsed.errors.is_empty());
+        assert!(parsed.rest.is_empty());
+        assert_eq!(actual, expected);
+    }
+
+    #[test_case(
+        StringFormat::json(),
+        r#""a\qb""#,
+        r#"\q"#,
+        "I didn't expect escape sequence `\\q`"
+    )]
+    #[test_case(
+        StringFormat::json(),
+        r#""\u12""#,
+        r#"\u12"#,
+        "I expected 4 hex digits in `\\u12`"
+    )]
+    #[test_case(
+        StringFormat::json(),
+        r#""\uDE00""#,
+        r#"\uDE00"#,
+        "I didn't expect unpaired surrogate `\\uDE00`"
+    )]
+    #[test_case(
+        StringFormat::rust(),
+        r#""\u{110000}""#,
+        r#"\u{110000}"#,
+        "I didn't expect `\\u{110000}`, it is not a valid code point"
+    )]
+    #[test_case(
+        StringFormat::rust(),
+        r#""\u{}""#,
+        r#"\u{"#,
+        "I expected 1 to 6 hex digits between `{` and `}` in `\\u{`"
+    )]
+    #[test_case(
+        StringFormat::rust(),
+        r#""\x80""#,
+        r#"\x80"#,
+        "I didn't expect `\\x80`, it is not a valid code point"
+    )]
+    #[test_case(StringFormat::json(), "\"foo\nbar\"", "", "I expected closing `\"`")]
+    #[test_case(StringFormat::rust(), "\"foo", "", "I expected closing `\"`")]
+    fn problems(format: StringFormat, input: &str, span: &str, expected: &str) {
+        let parsed = State::parse(input, string_literal(format));
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].span.as_ref(), span);
+        assert_eq!(parsed.errors[0].problem.to_string(), expected);
+    }
+
+    #[test]
+    fn reports_every_invalid_escape() {
+        let parsed = State::parse(r#""\q\z""#, string_literal(StringFormat::json()));
+
+        assert_eq!(parsed.errors.len(), 2);
+        assert!(parsed.rest.is_empty());
+    }
+
+    #[test_case(StringFormat::json(), r#"a\"b\n"#, "a\"b\n")]
+    #[test_case(StringFormat::json(), r#"\u00e9\uD83D\uDE00"#, "\u{e9}\u{1F600}")]
+    #[test_case(StringFormat::rust(), "a\\\n    b\\u{e9}", "ab\u{e9}")]
+    #[test_case(StringFormat::c(), r#"\101\x42\U00000043"#, "ABC")]
+    #[test_case(StringFormat::json(), r#"\q"#, r#"\q"#)]
+    fn unescape_text(format: StringFormat, text: &str, expected: &str) {
+        assert_eq!(unescape(&format, text), expected);
+    }
+}
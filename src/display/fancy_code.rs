@@ -3,8 +3,8 @@ use itertools::Itertools;
 use std::cmp::{Ordering, PartialOrd};
 use std::fmt::{Display, Error, Formatter};
 use std::str::Lines;
-use termion::color::Color;
-use termion::{color, style};
+use super::color::Color;
+use super::{color, style};
 
 pub type DisplayString = String;
 
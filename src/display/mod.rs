@@ -1,7 +1,13 @@
-use crate::{Node, NodeId, ParseError, Parsed, Span};
+use crate::{Diagnostics, Node, NodeId, ParseError, Parsed, Span};
 use std::fmt::{Display, Error, Formatter};
+#[cfg(feature = "tty")]
 use termion::{color, style};
 
+#[cfg(not(feature = "tty"))]
+mod plain;
+#[cfg(not(feature = "tty"))]
+use plain::{color, style};
+
 mod fancy_code;
 use fancy_code::FancyCode;
 
@@ -67,29 +73,48 @@ impl Display for Parsed {
             writeln!(f, "NO PROBLEMS")?;
         } else {
             writeln!(f, "PROBLEMS:")?;
-            for ParseError {
-                problem,
-                span,
-                context,
-            } in self.errors.iter()
-            {
-                writeln!(
-                    f,
-                    "{}{:-^80}{}",
-                    color::Fg(color::Red),
-                    " SYNTAX ERROR ",
-                    style::Reset
-                )?;
-                if let Some(context) = context.last() {
-                    write!(f, "I was parsing {} when ", context.node)?;
-                }
-                writeln!(f, "found issue:")?;
-
-                FancyCode::new(&self.input)
-                    .with_desc(span, format!("{}", problem).as_str(), color::LightRed)
-                    .fmt(f)?;
-            }
+            fmt_errors(f, &self.input, &self.errors, self.source_name.as_deref())?;
         }
         Ok(())
     }
 }
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        fmt_errors(f, &self.input, &self.errors, self.source_name.as_deref())
+    }
+}
+
+fn fmt_errors(
+    f: &mut Formatter<'_>,
+    input: &Span,
+    errors: &[ParseError],
+    source_name: Option<&str>,
+) -> Result<(), Error> {
+    for ParseError {
+        problem,
+        span,
+        context,
+    } in errors.iter()
+    {
+        writeln!(
+            f,
+            "{}{:-^80}{}",
+            color::Fg(color::Red),
+            " SYNTAX ERROR ",
+            style::Reset
+        )?;
+        if let Some(context) = context.last() {
+            write!(f, "I was parsing {} when ", context.node)?;
+        }
+        writeln!(f, "found issue:")?;
+        if let Some(source_name) = source_name {
+            writeln!(f, "--> {}", source_name)?;
+        }
+
+        FancyCode::new(input)
+            .with_desc(span, format!("{}", problem).as_str(), color::LightRed)
+            .fmt(f)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+//! Stand-ins for the `termion` colors and styles used by `FancyCode`, printing nothing.
+//! Without the `tty` feature diagnostics show the same content, just without colors.
+
+pub mod color {
+    use std::fmt::{Display, Error, Formatter};
+
+    pub trait Color {}
+
+    pub struct Fg<C: Color>(pub C);
+
+    impl<C: Color> Display for Fg<C> {
+        fn fmt(&self, _f: &mut Formatter<'_>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    macro_rules! colors {
+        ($($color: ident),*) => {
+            $(
+                #[derive(Clone, Copy)]
+                pub struct $color;
+                impl Color for $color {}
+            )*
+        };
+    }
+
+    colors!(Red, LightRed, Cyan, LightWhite, LightBlack);
+}
+
+pub mod style {
+    use std::fmt::{Display, Error, Formatter};
+
+    pub struct Reset;
+    pub struct Bold;
+
+    impl Display for Reset {
+        fn fmt(&self, _f: &mut Formatter<'_>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl Display for Bold {
+        fn fmt(&self, _f: &mut Formatter<'_>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+}
@@ -4,17 +4,23 @@
 mod macros;
 
 mod core {
+    mod cursor;
     mod offset;
+    mod parse;
     mod parsed;
     mod parser;
     mod span;
     mod state;
+    mod token;
 
+    pub use cursor::*;
     pub use offset::*;
+    pub use parse::*;
     pub use parsed::*;
     pub use parser::*;
     pub use span::*;
     pub use state::*;
+    pub use token::*;
 }
 
 mod cst {
@@ -27,7 +33,6 @@ mod cst {
 
 mod ast;
 
-#[cfg(feature = "tty")]
 mod display;
 
 pub use crate::core::*;
@@ -17,3 +17,18 @@ macro_rules! node_ids {
         }
     };
 }
+
+/// Union of character classes, named ones of this crate like `alpha`, chars and strings of chars,
+/// e.g. `class!(alpha | digit | '_')`.
+#[macro_export]
+macro_rules! class {
+    (@item $name: ident) => {
+        $crate::$name()
+    };
+    (@item $chars: literal) => {
+        $crate::CharClass::from($chars)
+    };
+    ($($item: tt)|+) => {
+        $crate::CharClass::union(vec![$($crate::class!(@item $item)),+])
+    };
+}